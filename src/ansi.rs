@@ -0,0 +1,527 @@
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+
+const TAB_WIDTH: usize = 8;
+
+/// A span of `StyledLine::text`, as byte offsets, drawn with one style.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StyleRun {
+    pub start: usize,
+    pub end: usize,
+    pub style: Style,
+}
+
+/// Where a line of output came from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputStream {
+    /// The child's stdout, or its terminal under a PTY, where both streams share one
+    #[default]
+    Stdout,
+    Stderr,
+    /// Messages from druns itself, such as restart separators
+    Druns,
+}
+
+/// One line of child output with its escape sequences turned into styles.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StyledLine {
+    /// The printable text, with escape sequences removed and tabs expanded
+    pub text: String,
+    /// Non-overlapping runs in ascending order; text outside any run is unstyled
+    pub runs: Vec<StyleRun>,
+    pub stream: OutputStream,
+    /// When the line was read from the child, or added by druns
+    pub arrived: Option<chrono::DateTime<chrono::Local>>,
+    /// A progress line still being redrawn with `\r`; the next line from the
+    /// same stream takes its place
+    pub partial: bool,
+    /// How many more times the line came in a row, collapsed into this one
+    pub repeats: usize,
+}
+
+impl StyledLine {
+    pub fn plain(text: impl Into<String>) -> Self {
+        StyledLine { text: text.into(), ..StyledLine::default() }
+    }
+
+    /// A line drawn entirely in one style, for messages from druns itself.
+    pub fn styled(text: impl Into<String>, style: Style) -> Self {
+        let mut line = StyledLine { stream: OutputStream::Druns, ..StyledLine::default() };
+        line.push(&text.into(), style);
+        line
+    }
+
+    /// Appends `text` drawn in `style`.
+    pub fn push(&mut self, text: &str, style: Style) {
+        if text.is_empty() {
+            return;
+        }
+        let start = self.text.len();
+        self.text.push_str(text);
+        let end = self.text.len();
+        if style == Style::default() {
+            return;
+        }
+        match self.runs.last_mut() {
+            Some(last) if last.end == start && last.style == style => last.end = end,
+            _ => self.runs.push(StyleRun { start, end, style }),
+        }
+    }
+
+    /// Patches `style` over the bytes `start..end`, on top of whatever styles
+    /// are already there, e.g. to mark search matches.
+    pub fn highlight(&mut self, start: usize, end: usize, style: Style) {
+        let end = end.min(self.text.len());
+        if start >= end {
+            return;
+        }
+        let mut bounds: Vec<usize> = vec![0, start, end, self.text.len()];
+        bounds.extend(self.runs.iter().flat_map(|run| [run.start, run.end]));
+        bounds.sort_unstable();
+        bounds.dedup();
+
+        let mut runs: Vec<StyleRun> = Vec::new();
+        for pair in bounds.windows(2) {
+            let (from, to) = (pair[0], pair[1]);
+            let mut segment = self
+                .runs
+                .iter()
+                .find(|run| run.start <= from && to <= run.end)
+                .map_or(Style::default(), |run| run.style);
+            if start <= from && to <= end {
+                segment = segment.patch(style);
+            }
+            if segment == Style::default() {
+                continue;
+            }
+            match runs.last_mut() {
+                Some(last) if last.end == from && last.style == segment => last.end = to,
+                _ => runs.push(StyleRun { start: from, end: to, style: segment }),
+            }
+        }
+        self.runs = runs;
+    }
+
+    /// The line as text with SGR sequences, which `AnsiParser` turns back
+    /// into the same line.
+    pub fn to_ansi(&self) -> String {
+        let mut out = String::with_capacity(self.text.len());
+        let mut pos = 0;
+        for run in &self.runs {
+            out.push_str(&self.text[pos..run.start]);
+            out.push_str(&format!("\x1b[{}m", sgr_codes(run.style)));
+            out.push_str(&self.text[run.start..run.end]);
+            out.push_str("\x1b[0m");
+            pos = run.end;
+        }
+        out.push_str(&self.text[pos..]);
+        out
+    }
+
+    pub fn to_line(&self) -> Line<'static> {
+        let mut spans = Vec::with_capacity(self.runs.len() * 2 + 1);
+        let mut pos = 0;
+        for run in &self.runs {
+            if run.start > pos {
+                spans.push(Span::raw(self.text[pos..run.start].to_string()));
+            }
+            spans.push(Span::styled(self.text[run.start..run.end].to_string(), run.style));
+            pos = run.end;
+        }
+        if pos < self.text.len() || spans.is_empty() {
+            spans.push(Span::raw(self.text[pos..].to_string()));
+        }
+        Line::from(spans)
+    }
+}
+
+/// Current SGR attributes, kept separately from `Style` so that resets
+/// clear attributes instead of recording them as removals.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct SgrState {
+    fg: Option<Color>,
+    bg: Option<Color>,
+    modifiers: Modifier,
+}
+
+impl SgrState {
+    fn style(&self) -> Style {
+        let mut style = Style::default().add_modifier(self.modifiers);
+        style.fg = self.fg;
+        style.bg = self.bg;
+        style
+    }
+
+    fn apply(&mut self, params: &str) {
+        let params: Vec<&str> = params.split(';').collect();
+        let mut i = 0;
+        while i < params.len() {
+            let param = params[i];
+            i += 1;
+            // ITU T.416 colon form, e.g. 38:2::255:0:0 or 38:5:208
+            if param.contains(':') {
+                let sub: Vec<&str> = param.split(':').collect();
+                let target = sub[0].parse::<u16>().unwrap_or(0);
+                let sub: Vec<u16> = sub[1..].iter().map(|s| s.parse().unwrap_or(0)).collect();
+                let color = match sub.first() {
+                    Some(5) => sub.get(1).map(|&n| indexed_color(n as u8)),
+                    // The colour space id is optional, so take the last three values
+                    Some(2) if sub.len() >= 4 => {
+                        let rgb = &sub[sub.len() - 3..];
+                        Some(Color::Rgb(rgb[0] as u8, rgb[1] as u8, rgb[2] as u8))
+                    }
+                    _ => None,
+                };
+                match target {
+                    38 => self.fg = color.or(self.fg),
+                    48 => self.bg = color.or(self.bg),
+                    4 => self.set_modifier(Modifier::UNDERLINED, sub.first() != Some(&0)),
+                    _ => {}
+                }
+                continue;
+            }
+            let code = if param.is_empty() { 0 } else { param.parse::<u16>().unwrap_or(u16::MAX) };
+            match code {
+                0 => *self = SgrState::default(),
+                1 => self.set_modifier(Modifier::BOLD, true),
+                2 => self.set_modifier(Modifier::DIM, true),
+                3 => self.set_modifier(Modifier::ITALIC, true),
+                4 => self.set_modifier(Modifier::UNDERLINED, true),
+                5 => self.set_modifier(Modifier::SLOW_BLINK, true),
+                6 => self.set_modifier(Modifier::RAPID_BLINK, true),
+                7 => self.set_modifier(Modifier::REVERSED, true),
+                8 => self.set_modifier(Modifier::HIDDEN, true),
+                9 => self.set_modifier(Modifier::CROSSED_OUT, true),
+                21 | 22 => self.set_modifier(Modifier::BOLD | Modifier::DIM, false),
+                23 => self.set_modifier(Modifier::ITALIC, false),
+                24 => self.set_modifier(Modifier::UNDERLINED, false),
+                25 => self.set_modifier(Modifier::SLOW_BLINK | Modifier::RAPID_BLINK, false),
+                27 => self.set_modifier(Modifier::REVERSED, false),
+                28 => self.set_modifier(Modifier::HIDDEN, false),
+                29 => self.set_modifier(Modifier::CROSSED_OUT, false),
+                30..=37 => self.fg = Some(basic_color(code - 30)),
+                39 => self.fg = None,
+                40..=47 => self.bg = Some(basic_color(code - 40)),
+                49 => self.bg = None,
+                90..=97 => self.fg = Some(bright_color(code - 90)),
+                100..=107 => self.bg = Some(bright_color(code - 100)),
+                38 | 48 => {
+                    let color = match params.get(i).and_then(|p| p.parse::<u16>().ok()) {
+                        Some(5) => {
+                            let color = params.get(i + 1).and_then(|p| p.parse::<u8>().ok()).map(indexed_color);
+                            i += 2;
+                            color
+                        }
+                        Some(2) => {
+                            let channel = |offset: usize| params.get(i + offset).and_then(|p| p.parse::<u8>().ok());
+                            let color = match (channel(1), channel(2), channel(3)) {
+                                (Some(r), Some(g), Some(b)) => Some(Color::Rgb(r, g, b)),
+                                _ => None,
+                            };
+                            i += 4;
+                            color
+                        }
+                        _ => None,
+                    };
+                    if let Some(color) = color {
+                        if code == 38 {
+                            self.fg = Some(color);
+                        } else {
+                            self.bg = Some(color);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn set_modifier(&mut self, modifier: Modifier, on: bool) {
+        if on {
+            self.modifiers.insert(modifier);
+        } else {
+            self.modifiers.remove(modifier);
+        }
+    }
+}
+
+/// SGR parameters that set `style` from a reset state.
+fn sgr_codes(style: Style) -> String {
+    let mut codes = vec!["0".to_string()];
+    let modifiers = [
+        (Modifier::BOLD, "1"),
+        (Modifier::DIM, "2"),
+        (Modifier::ITALIC, "3"),
+        (Modifier::UNDERLINED, "4"),
+        (Modifier::SLOW_BLINK, "5"),
+        (Modifier::RAPID_BLINK, "6"),
+        (Modifier::REVERSED, "7"),
+        (Modifier::HIDDEN, "8"),
+        (Modifier::CROSSED_OUT, "9"),
+    ];
+    for (modifier, code) in modifiers {
+        if style.add_modifier.contains(modifier) {
+            codes.push(code.to_string());
+        }
+    }
+    for (color, base) in [(style.fg, 30), (style.bg, 40)] {
+        let Some(color) = color else { continue };
+        let code = match color {
+            Color::Black => base.to_string(),
+            Color::Red => (base + 1).to_string(),
+            Color::Green => (base + 2).to_string(),
+            Color::Yellow => (base + 3).to_string(),
+            Color::Blue => (base + 4).to_string(),
+            Color::Magenta => (base + 5).to_string(),
+            Color::Cyan => (base + 6).to_string(),
+            Color::Gray => (base + 7).to_string(),
+            Color::DarkGray => (base + 60).to_string(),
+            Color::LightRed => (base + 61).to_string(),
+            Color::LightGreen => (base + 62).to_string(),
+            Color::LightYellow => (base + 63).to_string(),
+            Color::LightBlue => (base + 64).to_string(),
+            Color::LightMagenta => (base + 65).to_string(),
+            Color::LightCyan => (base + 66).to_string(),
+            Color::White => (base + 67).to_string(),
+            Color::Indexed(index) => format!("{};5;{index}", base + 8),
+            Color::Rgb(r, g, b) => format!("{};2;{r};{g};{b}", base + 8),
+            Color::Reset => continue,
+        };
+        codes.push(code);
+    }
+    codes.join(";")
+}
+
+/// Maps a 256-colour palette index, using the named colours for the first 16.
+pub fn indexed_color(index: u8) -> Color {
+    match index {
+        0..=7 => basic_color(index as u16),
+        8..=15 => bright_color(index as u16 - 8),
+        _ => Color::Indexed(index),
+    }
+}
+
+fn basic_color(index: u16) -> Color {
+    match index {
+        0 => Color::Black,
+        1 => Color::Red,
+        2 => Color::Green,
+        3 => Color::Yellow,
+        4 => Color::Blue,
+        5 => Color::Magenta,
+        6 => Color::Cyan,
+        _ => Color::Gray,
+    }
+}
+
+fn bright_color(index: u16) -> Color {
+    match index {
+        0 => Color::DarkGray,
+        1 => Color::LightRed,
+        2 => Color::LightGreen,
+        3 => Color::LightYellow,
+        4 => Color::LightBlue,
+        5 => Color::LightMagenta,
+        6 => Color::LightCyan,
+        _ => Color::White,
+    }
+}
+
+/// Turns raw lines of terminal output into `StyledLine`s. SGR attributes
+/// carry over from one line to the next, as they would on a real terminal,
+/// so each output stream should have its own parser.
+#[derive(Debug, Clone, Default)]
+pub struct AnsiParser {
+    state: SgrState,
+}
+
+impl AnsiParser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn parse_line(&mut self, raw: &str) -> StyledLine {
+        let mut line = StyledLine::default();
+        let mut text = String::new();
+        let mut column = 0;
+        let mut chars = raw.chars().peekable();
+
+        while let Some(ch) = chars.next() {
+            match ch {
+                '\x1b' => {
+                    line.push(&text, self.state.style());
+                    text.clear();
+                    match chars.next() {
+                        // CSI: parameters, intermediates, then a final byte in @..~
+                        Some('[') => {
+                            let mut params = String::new();
+                            let mut final_byte = None;
+                            for c in chars.by_ref() {
+                                if ('@'..='~').contains(&c) {
+                                    final_byte = Some(c);
+                                    break;
+                                }
+                                params.push(c);
+                            }
+                            if final_byte == Some('m') {
+                                self.state.apply(&params);
+                            }
+                        }
+                        // OSC and other strings end with BEL or ST (ESC \)
+                        Some(']') | Some('P') | Some('X') | Some('^') | Some('_') => {
+                            while let Some(c) = chars.next() {
+                                if c == '\x07' {
+                                    break;
+                                }
+                                if c == '\x1b' && chars.peek() == Some(&'\\') {
+                                    chars.next();
+                                    break;
+                                }
+                            }
+                        }
+                        // Charset designation takes one more character
+                        Some('(') | Some(')') | Some('*') | Some('+') => {
+                            chars.next();
+                        }
+                        _ => {}
+                    }
+                }
+                '\t' => {
+                    let spaces = TAB_WIDTH - column % TAB_WIDTH;
+                    text.push_str(&" ".repeat(spaces));
+                    column += spaces;
+                }
+                c if c.is_control() => {}
+                c => {
+                    text.push(c);
+                    column += 1;
+                }
+            }
+        }
+        line.push(&text, self.state.style());
+        line
+    }
+}
+
+/// Removes escape sequences and control characters, leaving the text a
+/// reader would see on screen.
+pub fn strip_ansi(raw: &str) -> String {
+    AnsiParser::new().parse_line(raw).text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn styles(line: &StyledLine) -> Vec<(&str, Style)> {
+        line.runs.iter().map(|run| (&line.text[run.start..run.end], run.style)).collect()
+    }
+
+    #[test]
+    fn test_plain_text_has_no_runs() {
+        let line = AnsiParser::new().parse_line("hello world");
+        assert_eq!(line.text, "hello world");
+        assert!(line.runs.is_empty());
+    }
+
+    #[test]
+    fn test_styled_line_covers_the_whole_text() {
+        let style = Style::default().fg(Color::Blue);
+        let line = StyledLine::styled("── restart ──", style);
+        assert_eq!(styles(&line), vec![("── restart ──", style)]);
+    }
+
+    #[test]
+    fn test_basic_colors_and_reset() {
+        let line = AnsiParser::new().parse_line("\x1b[31merror\x1b[0m: \x1b[1;92mok\x1b[m");
+        assert_eq!(line.text, "error: ok");
+        assert_eq!(styles(&line), vec![
+            ("error", Style::default().fg(Color::Red)),
+            ("ok", Style::default().fg(Color::LightGreen).add_modifier(Modifier::BOLD)),
+        ]);
+    }
+
+    #[test]
+    fn test_256_and_truecolor() {
+        let line = AnsiParser::new().parse_line("\x1b[38;5;208ma\x1b[48;2;1;2;3mb\x1b[38:2::10:20:30mc");
+        assert_eq!(styles(&line), vec![
+            ("a", Style::default().fg(Color::Indexed(208))),
+            ("b", Style::default().fg(Color::Indexed(208)).bg(Color::Rgb(1, 2, 3))),
+            ("c", Style::default().fg(Color::Rgb(10, 20, 30)).bg(Color::Rgb(1, 2, 3))),
+        ]);
+    }
+
+    #[test]
+    fn test_indexed_color_uses_named_colors_for_first_16() {
+        assert_eq!(indexed_color(1), Color::Red);
+        assert_eq!(indexed_color(9), Color::LightRed);
+        assert_eq!(indexed_color(15), Color::White);
+        assert_eq!(indexed_color(16), Color::Indexed(16));
+    }
+
+    #[test]
+    fn test_modifiers_turn_off() {
+        let line = AnsiParser::new().parse_line("\x1b[3;4;7mx\x1b[23;24my\x1b[27mz");
+        assert_eq!(styles(&line), vec![
+            ("x", Style::default().add_modifier(Modifier::ITALIC | Modifier::UNDERLINED | Modifier::REVERSED)),
+            ("y", Style::default().add_modifier(Modifier::REVERSED)),
+        ]);
+        assert_eq!(line.text, "xyz");
+    }
+
+    #[test]
+    fn test_style_carries_across_lines() {
+        let mut parser = AnsiParser::new();
+        parser.parse_line("\x1b[33mstart");
+        let line = parser.parse_line("continued");
+        assert_eq!(styles(&line), vec![("continued", Style::default().fg(Color::Yellow))]);
+    }
+
+    #[test]
+    fn test_non_sgr_sequences_are_dropped() {
+        let line = AnsiParser::new().parse_line("\x1b]0;title\x07\x1b[2K\x1b[1Gdone\x1b(B\x07");
+        assert_eq!(line.text, "done");
+        assert!(line.runs.is_empty());
+    }
+
+    #[test]
+    fn test_tabs_expand_to_tab_stops() {
+        let line = AnsiParser::new().parse_line("ab\tc\x1b[1m\td");
+        assert_eq!(line.text, "ab      c       d");
+    }
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(strip_ansi("\x1b[1;31mFAILED\x1b[0m tests::x"), "FAILED tests::x");
+    }
+
+    #[test]
+    fn test_to_line_keeps_unstyled_gaps() {
+        let line = AnsiParser::new().parse_line("a \x1b[1mb\x1b[0m c");
+        let rendered = line.to_line();
+        let contents: Vec<&str> = rendered.spans.iter().map(|span| span.content.as_ref()).collect();
+        assert_eq!(contents, vec!["a ", "b", " c"]);
+        assert_eq!(rendered.spans[1].style, Style::default().add_modifier(Modifier::BOLD));
+    }
+
+    #[test]
+    fn test_to_ansi_round_trips() {
+        let raw = "plain \x1b[1;31mbold red\x1b[0m \x1b[38;5;208;48;2;1;2;3;4mfancy\x1b[0m \x1b[96mbright";
+        let line = AnsiParser::new().parse_line(raw);
+        let again = AnsiParser::new().parse_line(&line.to_ansi());
+        assert_eq!(again.text, line.text);
+        assert_eq!(again.runs, line.runs);
+    }
+
+    #[test]
+    fn test_highlight_patches_existing_styles() {
+        let mut line = AnsiParser::new().parse_line("ab\x1b[31mcd\x1b[0mef");
+        let marked = Style::default().bg(Color::Yellow);
+        line.highlight(1, 3, marked);
+        assert_eq!(
+            styles(&line),
+            vec![("b", marked), ("c", Style::default().fg(Color::Red).bg(Color::Yellow)), ("d", Style::default().fg(Color::Red))]
+        );
+        assert_eq!(line.text, "abcdef");
+    }
+}
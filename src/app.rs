@@ -1,58 +1,100 @@
 use crate::cli::Args;
 use crate::config::Config;
-use crate::disk::monitor_disk;
-#[cfg(feature = "experimental-pcap")]
-use crate::network::monitor_network;
-use crate::process::ProcessManager;
-use crate::ui::{draw_ui, DrawContext};
+use crate::keys::KeySpec;
+use crate::output_log::{OutputLog, OutputLogs};
+use crate::highlight::{self, Highlighter};
+use crate::search::{step, LineFilter, Prompt, PromptEvent, PromptKind, Search};
+use crate::procfile::{self, ProcessDefinition};
+use crate::process::{OutputMode, SpawnOptions};
+use crate::process_tree::{ProcessRow, TreeSort, PANEL_SIGNALS};
+use crate::restart::RestartTracker;
+use crate::signals::{is_termination, ForwardedSignals};
+use crate::supervised::{SupervisedProcess, SupervisionOptions};
+use crate::ui::{draw_ui, inner_size, pane_rects, DrawContext, PaneContext};
+use crate::ui_layout::PaneLayout;
+use crate::ui_output::{OutputView, StreamFilter, TimestampMode};
+use crate::watch::{FileWatcher, IgnoreRules};
 use crossterm::{
-    event::{self, Event, KeyCode, KeyModifiers},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use ratatui::{backend::CrosstermBackend, Terminal};
-use std::fs::OpenOptions;
-use std::io::stdout;
-use std::sync::Arc;
-use sysinfo::{Pid, System};
-use tokio::signal;
+use ratatui::{
+    backend::CrosstermBackend,
+    layout::Rect,
+    style::{Modifier, Style},
+    Terminal,
+};
+use std::io::{stdout, IsTerminal};
+use std::os::unix::process::ExitStatusExt;
+use std::process::ExitStatus;
+use std::path::PathBuf;
+use sysinfo::System;
 use tokio::sync::mpsc;
 use tokio::time::{sleep, Duration, Instant};
 
+/// Exit code when a run was stopped by `--timeout`, the same as timeout(1) uses.
+pub const TIMEOUT_EXIT_CODE: i32 = 124;
+
+/// A signal picked in the process panel, sent once the user confirms it.
+struct PendingSignal {
+    pid: u32,
+    command: String,
+    signal: i32,
+    name: &'static str,
+}
+
 pub struct App {
-    #[allow(dead_code)]
     args: Args,
     config: Config,
-    process_manager: ProcessManager,
-    #[allow(dead_code)]
-    log_file: Option<Arc<std::sync::Mutex<std::fs::File>>>,
-    output_lines: Vec<String>,
-    output_rx: mpsc::UnboundedReceiver<String>,
+    /// The commands being run: just one, or one per Procfile entry
+    processes: Vec<SupervisedProcess>,
+    /// The process whose output, keys and sidebar details are in focus
+    selected: usize,
+    pane_layout: PaneLayout,
+    /// Which output streams the panes show
+    stream_filter: StreamFilter,
+    timestamps: TimestampMode,
+    // Searching and filtering the output
+    search: Option<Search>,
+    line_filter: Option<LineFilter>,
+    prompt: Option<Prompt>,
+    highlighter: Highlighter,
+    /// The match `n`/`N` last jumped to, by line id so it stays put as lines come and go
+    search_match: Option<usize>,
+    /// A JSON log line pretty-printed in place of the selected pane's output
+    expanded_json: Option<String>,
+    /// How many lines the pretty-printed JSON is scrolled down
+    json_scroll: usize,
+    /// The selected process' tree is listed in place of its output
+    show_tree: bool,
+    tree_sort: TreeSort,
+    /// The process picked in the list, by PID; the first one when unset or gone
+    tree_selected: Option<u32>,
+    pending_signal: Option<PendingSignal>,
     terminal: Terminal<CrosstermBackend<std::io::Stdout>>,
     current_width: u16,
     current_height: u16,
     animation_frame: u32,
     system: System,
-    #[allow(dead_code)]
-    network_stats_rx: Option<mpsc::UnboundedReceiver<(u64, u64)>>,
-    disk_stats_rx: mpsc::UnboundedReceiver<(u64, u64)>,
-    start_time: Instant,
-    // History for averaging
-    cpu_history: Vec<f32>,
-    memory_history: Vec<u64>,
-    disk_read_history: Vec<u64>,
-    disk_write_history: Vec<u64>,
-    // Display values (averaged)
-    display_cpu: f32,
-    display_memory: u64,
-    display_disk_read: u64,
-    display_disk_write: u64,
-    iteration_count: u32,
-    command: Vec<String>,
     pwd: String,
-    // Output scrolling state
-    follow_mode: bool,
-    scroll_offset: usize,
+    // Keyboard passthrough to the selected process
+    input_mode: bool,
+    prefix_pending: bool,
+    prefix_key: KeySpec,
+    // Stopping the processes
+    signals: ForwardedSignals,
+    grace_period: Duration,
+    /// Set once the user or a signal asked druns to stop; exit when everything has
+    quitting: bool,
+    // Rerunning on file changes
+    _watcher: Option<FileWatcher>,
+    watch_rx: Option<mpsc::UnboundedReceiver<PathBuf>>,
+    watch_debounce: Duration,
+    /// Rerun once no further change arrives before this point
+    watch_deadline: Option<Instant>,
+    pending_trigger: Option<PathBuf>,
+    last_trigger: Option<String>,
 }
 
 impl App {
@@ -81,55 +123,168 @@ impl App {
         config.app.layout.sidebar_width = effective_sidebar_width;
         config.app.layout.max_command_lines = args.max_command_lines;
         config.app.output.max_output_lines = args.max_output_lines;
+        if args.unlimited_scrollback {
+            config.app.output.unlimited_scrollback = true;
+        }
         if args.no_animate {
             config.app.animation.animation_enabled = false;
         } else {
             config.app.animation.animation_enabled = args.animation;
         }
+        if let Some(prefix_key) = &args.prefix_key {
+            config.app.input.prefix_key = prefix_key.clone();
+        }
+        let prefix_key = KeySpec::parse(config.app.prefix_key())
+            .ok_or_else(|| format!("Invalid prefix key: {}", config.app.prefix_key()))?;
+        if let Some(grace_period) = args.grace_period {
+            config.app.process.grace_period_secs = grace_period;
+        }
+        let grace_period = Duration::try_from_secs_f64(config.app.grace_period_secs())
+            .map_err(|_| format!("Invalid grace period: {}", config.app.grace_period_secs()))?;
+        if let Some(restart) = args.restart {
+            config.app.process.restart_policy = restart;
+        }
+        if let Some(max_restarts) = args.max_restarts {
+            config.app.process.max_restarts = Some(max_restarts);
+        }
+        if let Some(network_device) = &args.network_device {
+            config.app.process.network_device = network_device.clone();
+        }
+        let restart_tracker = RestartTracker::new(config.app.process.restart_policy, config.app.restart_limits());
+        let timeout = match args.timeout {
+            Some(timeout) => Some(timeout),
+            None => config.app.timeout()?,
+        };
+        if let Some(debounce) = args.debounce {
+            config.app.watch.watch_debounce_ms = debounce;
+        }
+        let watch_debounce = Duration::from_millis(config.app.watch_debounce_ms());
+        if let Some(layout) = args.layout {
+            config.app.layout.pane_layout = layout;
+        }
+        let pane_layout = config.app.layout.pane_layout;
+        if let Some(timestamps) = args.timestamps {
+            config.app.output.timestamps = timestamps;
+        }
+        if args.log_timestamps {
+            config.app.output.log_timestamps = true;
+        }
+        if let Some(dedup) = args.dedup {
+            config.app.output.dedup = dedup;
+        }
+        // Rules from --highlights files come after the config's, so they're drawn on top
+        let mut highlight_rules = config.highlights.clone();
+        for path in &args.highlights {
+            highlight_rules.extend(highlight::load(path)?);
+        }
+        let highlighter = Highlighter::new(&highlight_rules, &config.theme)?;
 
-        // Open log file if specified
-        let log_file = if let Some(log_path) = &args.log {
-            Some(Arc::new(std::sync::Mutex::new(OpenOptions::new().create(true).append(true).open(log_path)?)))
-        } else {
-            None
+        // A Procfile or [[process]] tables run several commands, otherwise it's just the one
+        let definitions = process_definitions(&args, &command, &config)?;
+        if command.is_empty() && definitions.is_empty() {
+            return Err("No command provided".into());
+        }
+        // Catch signals before any child exists so none slip past to druns' default handlers
+        let signals = ForwardedSignals::new()?;
+
+        // Open the log files if specified; stderr shares the main log unless given its own
+        let log_timestamps = config.app.output.log_timestamps;
+        let open_log = |path: &Option<String>| -> std::io::Result<Option<OutputLog>> {
+            let Some(path) = path else { return Ok(None) };
+            let log = OutputLog::open(path, args.log_raw)?;
+            Ok(Some(if log_timestamps { log.with_timestamps() } else { log }))
         };
+        let logs = OutputLogs { log: open_log(&args.log)?, stderr_log: open_log(&args.log_stderr)? };
 
         // Get terminal size
         let (width, height) = crossterm::terminal::size()?;
 
         // Get current working directory
         let pwd = std::env::current_dir()?.to_string_lossy().to_string();
-        let sidebar_width = config.app.layout.sidebar_width;
-        let app_width = width.saturating_sub(sidebar_width);
-
-        // Override COLUMNS
-        std::env::set_var("COLUMNS", app_width.to_string());
-
-        // Create process manager
-        let mut process_manager = ProcessManager::new(&command)?;
-
-        // Start output reading
-        let output_rx = process_manager.start_output_reading(log_file.clone());
-
-        // Channel for network stats
-        #[cfg(feature = "experimental-pcap")]
-        let network_stats_rx = {
-            let (tx, rx) = mpsc::unbounded_channel::<(u64, u64)>();
-            let pid = process_manager.pid;
-            tokio::spawn(async move {
-                monitor_network(pid, tx).await;
-            });
-            Some(rx)
+
+        // Watch for changes before the first run so none are missed
+        let (watcher, watch_rx) = if args.watch.is_empty() {
+            (None, None)
+        } else {
+            let cwd = PathBuf::from(&pwd);
+            let paths: Vec<PathBuf> = args.watch.iter().map(|path| cwd.join(path)).collect();
+            let mut rules = IgnoreRules::default();
+            if config.app.watch.watch_gitignore {
+                rules.add_gitignore(&cwd);
+                for path in paths.iter().filter(|path| path.is_dir() && **path != cwd) {
+                    rules.add_gitignore(path);
+                }
+            }
+            let mut patterns = config.app.watch.watch_ignore.clone();
+            patterns.extend(args.ignore.iter().cloned());
+            rules.add_patterns(&cwd, &patterns);
+            // Our own log would otherwise retrigger every run
+            for log_path in args.log.iter().chain(&args.log_stderr) {
+                rules.add_path(&cwd.join(log_path));
+            }
+            let (watcher, rx) = FileWatcher::new(&paths, rules)?;
+            (Some(watcher), Some(rx))
+        };
+
+        // Run under a PTY unless asked not to, or unless druns itself isn't on a terminal
+        let mode = if args.pipes || !stdout().is_terminal() {
+            OutputMode::Pipes
+        } else {
+            OutputMode::Pty
         };
-        #[cfg(not(feature = "experimental-pcap"))]
-        let network_stats_rx = None;
 
-        // Channel for disk stats
-        let (disk_stats_tx, disk_stats_rx) = mpsc::unbounded_channel::<(u64, u64)>();
-        let pid = process_manager.pid;
-        tokio::spawn(async move {
-            monitor_disk(pid, disk_stats_tx).await;
-        });
+        // Each process gets its own terminal, sized to its pane
+        let count = definitions.len().max(1);
+        let rects = pane_rects(Rect::new(0, 0, width, height), config.app.layout.sidebar_width, count, pane_layout);
+        let supervision = SupervisionOptions {
+            max_output_lines: config.app.output.max_output_lines,
+            unlimited_scrollback: config.app.output.unlimited_scrollback,
+            separator_style: Style::default().fg(config.theme.secondary).add_modifier(Modifier::BOLD),
+            restart_tracker,
+            timeout,
+            dedup: config.app.output.dedup,
+            network_device: config.app.process.network_device.clone(),
+        };
+        let mut processes = Vec::new();
+        if definitions.is_empty() {
+            let (cols, rows) = inner_size(rects[0]);
+            let shell = args.shell.then(|| config.app.shell().to_string());
+            let spawn_options = SpawnOptions { mode, cols, rows, shell };
+            let name = command[0].rsplit('/').next().unwrap_or(&command[0]).to_string();
+            processes.push(SupervisedProcess::spawn(
+                name,
+                command,
+                spawn_options,
+                logs,
+                supervision,
+            )?);
+        } else {
+            for (definition, rect) in definitions.into_iter().zip(rects) {
+                let (cols, rows) = inner_size(rect);
+                // Procfile commands are shell command lines
+                let shell = Some(config.app.shell().to_string());
+                let spawn_options = SpawnOptions { mode, cols, rows, shell };
+                let logs = logs.with_prefix(&definition.name);
+                let process = SupervisedProcess::spawn(
+                    definition.name.clone(),
+                    vec![definition.command],
+                    spawn_options,
+                    logs,
+                    supervision.clone(),
+                )
+                .map_err(|e| format!("Cannot start {}: {e}", definition.name));
+                match process {
+                    Ok(process) => processes.push(process),
+                    Err(e) => {
+                        // Don't leave the ones already started running
+                        for process in &mut processes {
+                            let _ = process.process_manager.kill().await;
+                        }
+                        return Err(e.into());
+                    }
+                }
+            }
+        }
 
         // Setup terminal
         let mut stdout = stdout();
@@ -142,69 +297,95 @@ impl App {
         let current_height = height;
         let animation_frame = 0;
         let system = System::new_all();
-        let start_time = Instant::now();
-        let output_lines = Vec::new();
-        let cpu_history = Vec::new();
-        let memory_history = Vec::new();
-        let disk_read_history = Vec::new();
-        let disk_write_history = Vec::new();
-        let display_cpu = 0.0;
-        let display_memory = 0;
-        let display_disk_read = 0;
-        let display_disk_write = 0;
-        let iteration_count = 0;
-        let follow_mode = true; // Start in follow mode
-        let scroll_offset = 0;
-
-        Ok(App {
+        let input_mode = args.interactive;
+        let timestamps = config.app.output.timestamps;
+
+        let mut app = App {
             args,
             config,
-            process_manager,
-            log_file,
-            output_lines,
-            output_rx,
+            processes,
+            selected: 0,
+            pane_layout,
+            stream_filter: StreamFilter::default(),
+            timestamps,
+            search: None,
+            line_filter: None,
+            prompt: None,
+            highlighter,
+            search_match: None,
+            expanded_json: None,
+            json_scroll: 0,
+            show_tree: false,
+            tree_sort: TreeSort::default(),
+            tree_selected: None,
+            pending_signal: None,
             terminal,
             current_width,
             current_height,
             animation_frame,
             system,
-            network_stats_rx,
-            disk_stats_rx,
-            start_time,
-            cpu_history,
-            memory_history,
-            disk_read_history,
-            disk_write_history,
-            display_cpu,
-            display_memory,
-            display_disk_read,
-            display_disk_write,
-            iteration_count,
-            command,
             pwd,
-            follow_mode,
-            scroll_offset,
-        })
+            input_mode,
+            prefix_pending: false,
+            prefix_key,
+            signals,
+            grace_period,
+            quitting: false,
+            _watcher: watcher,
+            watch_rx,
+            watch_debounce,
+            watch_deadline: None,
+            pending_trigger: None,
+            last_trigger: None,
+        };
+        app.update_views();
+        Ok(app)
     }
 
     pub async fn run(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         loop {
             tokio::select! {
-                _ = signal::ctrl_c() => {
-                    let _ = self.process_manager.kill().await;
-                    break;
-                }
-                Some(line) = self.output_rx.recv() => {
-                    self.output_lines.push(line);
-                    if self.output_lines.len() > self.config.app.output.max_output_lines {
-                        self.output_lines.remove(0);
+                signal = self.signals.recv() => {
+                    if is_termination(signal) {
+                        self.quit();
+                    }
+                    for process in &mut self.processes {
+                        process.forward_signal(signal, self.grace_period).await;
                     }
                 }
+                Some(path) = recv_change(&mut self.watch_rx) => {
+                    // Each change pushes the rerun back until things settle
+                    self.pending_trigger = Some(path);
+                    self.watch_deadline = Some(Instant::now() + self.watch_debounce);
+                }
                 _ = sleep(Duration::from_millis(20)) => {
-                    // Check if child is still running
-                    if let Ok(Some(_)) = self.process_manager.try_wait() {
+                    for process in &mut self.processes {
+                        process.drain_output();
+                    }
+
+                    // Check if the children are still running
+                    if !self.check_exits().await {
                         break;
                     }
+                    if self.watch_deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                        self.watch_deadline = None;
+                        let trigger = self.pending_trigger.take().map(|path| {
+                            path.strip_prefix(&self.pwd).unwrap_or(&path).display().to_string()
+                        });
+                        let cause = format!("{} changed", trigger.as_deref().unwrap_or("a file"));
+                        self.last_trigger = trigger;
+                        if !self.quitting {
+                            for process in &mut self.processes {
+                                process.request_restart(cause.clone(), false, self.grace_period).await;
+                            }
+                        }
+                    }
+                    for process in &mut self.processes {
+                        process.check_timeout(self.grace_period).await;
+                        process.check_stop_deadline().await;
+                        process.check_restart_timer(self.grace_period);
+                        process.check_respawn();
+                    }
 
                     // Check for resize and key events
                     if event::poll(Duration::from_millis(10))? {
@@ -212,181 +393,129 @@ impl App {
                             Event::Resize(new_width, new_height) => {
                                 self.current_width = new_width;
                                 self.current_height = new_height;
-                                self.terminal.resize(ratatui::layout::Rect::new(0, 0, self.current_width, new_height))?;
+                                self.terminal.resize(Rect::new(0, 0, self.current_width, new_height))?;
+                            }
+                            Event::Key(key) if key.kind != KeyEventKind::Release && self.handle_key(key).await => {
+                                break;
                             }
-                             Event::Key(key) => {
-                                 let page_height = (self.current_height.saturating_sub(4) as usize).min(20);
-                                 let total_lines = self.output_lines.len();
-                                 let visible_height = page_height;
-                                 let max_scroll_up = total_lines.saturating_sub(visible_height);
-                                 match key.code {
-                                     KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
-                                         let _ = self.process_manager.kill().await;
-                                         break;
-                                     }
-                                     KeyCode::Char('f') => {
-                                         // Toggle follow mode
-                                         self.follow_mode = !self.follow_mode;
-                                         if self.follow_mode {
-                                             self.scroll_offset = 0;
-                                         }
-                                     }
-                                     KeyCode::Up => {
-                                         // Scroll up by one line
-                                         if self.follow_mode {
-                                             self.follow_mode = false;
-                                         }
-                                         self.scroll_offset += 1;
-                                     }
-                                     KeyCode::Down => {
-                                         // Scroll down by one line
-                                         self.scroll_offset = self.scroll_offset.saturating_sub(1);
-                                         if self.scroll_offset == 0 {
-                                             self.follow_mode = true;
-                                         }
-                                     }
-                                     KeyCode::PageUp => {
-                                         // Scroll up by page height
-                                         if self.follow_mode {
-                                             self.follow_mode = false;
-                                         }
-                                         self.scroll_offset += page_height;
-                                     }
-                                     KeyCode::PageDown => {
-                                         // Scroll down by page height
-                                         self.scroll_offset = self.scroll_offset.saturating_sub(page_height);
-                                         if self.scroll_offset == 0 {
-                                             self.follow_mode = true;
-                                         }
-                                     }
-                                     KeyCode::Home => {
-                                         // Scroll to top
-                                         self.follow_mode = false;
-                                         self.scroll_offset = max_scroll_up;
-                                     }
-                                     KeyCode::End => {
-                                         // Scroll to bottom and enable follow mode
-                                         self.follow_mode = true;
-                                         self.scroll_offset = 0;
-                                     }
-                                     KeyCode::Esc => {
-                                         // Return to end of log and enable follow mode
-                                         self.follow_mode = true;
-                                         self.scroll_offset = 0;
-                                     }
-                                     _ => {}
-                                 }
-                             }
                             _ => {}
                         }
                     }
 
-                    // Update COLUMNS
-                    let app_width = self.current_width.saturating_sub(self.config.app.layout.sidebar_width);
-                    std::env::set_var("COLUMNS", app_width.to_string());
+                    // Keep each child's terminal the same size as its output pane
+                    let rects = self.pane_rects();
+                    for (process, rect) in self.processes.iter_mut().zip(rects) {
+                        process.resize(inner_size(rect));
+                    }
 
                     // Refresh system info
                     self.system.refresh_all();
-
-                    // Calculate stats
-                    let cpu_percent = if let Some(process) = self.system.process(Pid::from(self.process_manager.pid as usize)) {
-                        process.cpu_usage()
-                    } else {
-                        0.0
-                    };
-                    let memory_used = if let Some(process) = self.system.process(Pid::from(self.process_manager.pid as usize)) {
-                        process.memory()
-                    } else {
-                        0
-                    };
-                    let memory_total = self.system.total_memory();
-
-                    // Get latest network stats
-                    let (child_network_rx, child_network_tx) = {
-                        #[cfg(feature = "experimental-pcap")]
-                        { self.network_stats_rx.as_mut().unwrap().try_recv().unwrap_or((0, 0)) }
-                        #[cfg(not(feature = "experimental-pcap"))]
-                        { (0, 0) }
-                    };
-
-                    // Get latest disk stats
-                    let mut disk_read = 0u64;
-                    let mut disk_write = 0u64;
-                    if let Ok((r, w)) = self.disk_stats_rx.try_recv() {
-                        disk_read = r;
-                        disk_write = w;
-                    }
-
-                    // Push to history every second (50 iterations)
-                    if self.iteration_count % 50 == 0 {
-                        self.cpu_history.push(cpu_percent);
-                        self.memory_history.push(memory_used);
-                        self.disk_read_history.push(disk_read);
-                        self.disk_write_history.push(disk_write);
-
-                        // Limit history to last 5 minutes (300 samples at 1s intervals)
-                        const MAX_HISTORY: usize = 300;
-                        if self.cpu_history.len() > MAX_HISTORY {
-                            self.cpu_history.remove(0);
-                            self.memory_history.remove(0);
-                            self.disk_read_history.remove(0);
-                            self.disk_write_history.remove(0);
-                        }
-                    }
-
-                    // Update display values every second (50 iterations)
-                    self.iteration_count += 1;
-                    if self.iteration_count % 50 == 0 {
-                        self.display_cpu = if !self.cpu_history.is_empty() {
-                            self.cpu_history.iter().sum::<f32>() / self.cpu_history.len() as f32
-                        } else {
-                            0.0
-                        };
-                        self.display_memory = if !self.memory_history.is_empty() {
-                            (self.memory_history.iter().sum::<u64>() as f64 / self.memory_history.len() as f64).round() as u64
-                        } else {
-                            0
-                        };
-                        self.display_disk_read = if !self.disk_read_history.is_empty() {
-                            (self.disk_read_history.iter().sum::<u64>() as f64 / self.disk_read_history.len() as f64).round() as u64
-                        } else {
-                            0
-                        };
-                        self.display_disk_write = if !self.disk_write_history.is_empty() {
-                            (self.disk_write_history.iter().sum::<u64>() as f64 / self.disk_write_history.len() as f64).round() as u64
-                        } else {
-                            0
-                        };
+                    for process in &mut self.processes {
+                        process.sample(&self.system);
                     }
+                    let memory_total = self.system.total_memory();
 
                     // Draw UI
                     let current_animation_frame = if self.config.app.animation.animation_enabled { self.animation_frame } else { 0 };
                     let theme = self.config.theme.clone();
+                    let screens: Vec<_> = self
+                        .processes
+                        .iter()
+                        .map(|process| {
+                            process
+                                .process_manager
+                                .screen()
+                                .filter(|screen| screen.is_shown(self.args.screen))
+                                .map(|screen| screen.lock())
+                        })
+                        .collect();
+                    let panes: Vec<PaneContext> = self
+                        .processes
+                        .iter()
+                        .zip(&screens)
+                        .enumerate()
+                        .map(|(index, (process, screen))| PaneContext {
+                            name: &process.name,
+                            output: &process.output,
+                            shown: &process.shown,
+                            screen: screen.as_ref().map(|parser| parser.screen()),
+                            follow_mode: process.follow_mode,
+                            scroll_offset: process.scroll_offset,
+                            view: OutputView { streams: self.stream_filter, filter: self.line_filter.as_ref(), json: Some(&self.config.app.json) },
+                            timestamps: self.timestamps,
+                            started_at: process.first_started_at,
+                            search_match: if index == self.selected { self.search_match } else { None },
+                            stopping: process.stop_deadline.is_some(),
+                            restarting: process.pending_restart,
+                            waiting_for_changes: process.waiting_for_changes,
+                            restart_in: process.restart_at.map(|at| at.saturating_duration_since(Instant::now())),
+                            exited: process.exited,
+                            timed_out: process.timed_out,
+                            cpu_percent: process.display_cpu,
+                            memory_used: process.display_memory,
+                        })
+                        .collect();
+                    let tree_selected = self.show_tree.then(|| self.selected_tree_row()).flatten();
+                    let confirm = self.pending_signal.as_ref().map(|pending| {
+                        let command: String = pending.command.chars().take(60).collect();
+                        format!("Send {} to {} ({command})?", pending.name, pending.pid)
+                    });
+                    let process = &self.processes[self.selected];
+                    let last_run = process.last_run.format("%H:%M:%S").to_string();
                     self.terminal.draw(|f| {
                         draw_ui(f, DrawContext {
-                            command: &self.command,
+                            command: &process.command,
                             pwd: &self.pwd,
-                            elapsed: self.start_time.elapsed(),
-                            pid: self.process_manager.pid,
-                            ppid: self.process_manager.ppid,
+                            elapsed: process.first_start.elapsed(),
+                            uptime: process.start_time.elapsed(),
+                            pid: process.process_manager.pid,
+                            ppid: process.process_manager.ppid,
                             animation_frame: current_animation_frame,
                             theme: &theme,
-                            output_lines: &self.output_lines,
+                            panes: &panes,
+                            selected: self.selected,
+                            search: self.search.as_ref(),
+                            prompt: self.prompt.as_ref(),
+                            highlighter: &self.highlighter,
+                            json: &self.config.app.json,
+                            expanded_json: self.expanded_json.as_deref(),
+                            json_scroll: self.json_scroll,
+                            process_tree: self.show_tree.then_some(process.tree.as_slice()),
+                            tree_sort: self.tree_sort,
+                            tree_selected: tree_selected.as_ref().map(|row| row.pid),
+                            confirm: confirm.as_deref(),
+                            layout: self.pane_layout,
                             sidebar_width: self.config.app.layout.sidebar_width,
                             max_command_lines: self.config.app.max_command_lines(),
-                            cpu_percent: self.display_cpu,
-                            memory_used: self.display_memory,
+                            cpu_percent: process.display_cpu,
+                            memory_used: process.display_memory,
                             memory_total,
-                            disk_read: self.display_disk_read,
-                            disk_write: self.display_disk_write,
-                            child_network_rx,
-                            child_network_tx,
-                            cpu_history: &self.cpu_history,
-                            memory_history: &self.memory_history,
-                            disk_read_history: &self.disk_read_history,
-                            disk_write_history: &self.disk_write_history,
-                            follow_mode: self.follow_mode,
-                            scroll_offset: self.scroll_offset,
+                            process_count: process.process_count,
+                            thread_count: process.thread_count,
+                            disk_read: process.display_disk_read,
+                            disk_write: process.display_disk_write,
+                            child_network_rx: process.network_rx,
+                            child_network_tx: process.network_tx,
+                            network_measured: process.network_measured,
+                            network_connections: process.network_connections,
+                            network_listening: process.network_listening,
+                            cpu_history: &process.cpu_history,
+                            memory_history: &process.memory_history,
+                            disk_read_history: &process.disk_read_history,
+                            disk_write_history: &process.disk_write_history,
+                            network_rx_history: &process.network_rx_history,
+                            network_tx_history: &process.network_tx_history,
+                            connection_history: &process.connection_history,
+                            input_mode: self.input_mode,
+                            watching: self.watch_rx.is_some(),
+                            last_trigger: self.last_trigger.as_deref(),
+                            last_run: &last_run,
+                            restart_count: process.restart_count,
+                            last_exit: process.last_exit,
+                            restart_status: process.restart_status(),
+                            timeout_left: process.timeout_left(),
+                            prefix_pending: self.prefix_pending,
+                            prefix_key: &self.prefix_key,
                             shine_amplitude: self.config.app.shine_amplitude(),
                             shine_frequency: self.config.app.shine_frequency(),
                             shine_base_intensity: self.config.app.shine_base_intensity(),
@@ -408,28 +537,475 @@ impl App {
         Ok(())
     }
 
+    /// Restarts or retires processes whose run just ended. Returns false once
+    /// druns should exit: every process has exited and none is waiting for a
+    /// change to rerun it.
+    async fn check_exits(&mut self) -> bool {
+        let watching = self.watch_rx.is_some();
+        // With one process and no watching, druns exits along with it and says so itself
+        let announce = watching || self.processes.len() > 1;
+        for process in &mut self.processes {
+            let Some(status) = process.check_exit() else { continue };
+            if process.pending_restart && !self.quitting {
+                process.complete_restart(status, self.grace_period);
+            } else if !self.quitting && process.stop_deadline.is_none() && process.schedule_restart(status) {
+                // Comes back after the backoff
+            } else {
+                // A timed-out run counts as finished, not as stopped by the user
+                let stopped = process.stop_deadline.is_some() && !process.timed_out;
+                let waiting = watching && !self.quitting && !stopped;
+                process.finish(status, waiting, announce);
+            }
+        }
+        self.processes
+            .iter()
+            .any(|process| {
+                process.is_running() || process.is_respawning() || process.waiting_for_changes || process.restart_at.is_some()
+            })
+    }
+
+    /// Stops rerunning anything; druns exits once every process has.
+    fn quit(&mut self) {
+        self.quitting = true;
+        for process in &mut self.processes {
+            process.restart_at = None;
+            process.pending_restart = false;
+            process.cancel_respawn();
+        }
+    }
+
+    fn pane_rects(&self) -> Vec<Rect> {
+        pane_rects(
+            Rect::new(0, 0, self.current_width, self.current_height),
+            self.config.app.layout.sidebar_width,
+            self.processes.len(),
+            self.pane_layout,
+        )
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index;
+        self.search_match = None;
+        self.expanded_json = None;
+    }
+
+    /// The selected process' tree in the order the panel lists it.
+    fn tree_rows(&self) -> Vec<ProcessRow> {
+        let mut rows = self.processes[self.selected].tree.clone();
+        self.tree_sort.sort(&mut rows);
+        rows
+    }
+
+    /// The process picked in the panel: the last one selected while it is
+    /// still running, otherwise the first.
+    fn selected_tree_row(&self) -> Option<ProcessRow> {
+        let mut rows = self.tree_rows();
+        let index = rows.iter().position(|row| Some(row.pid) == self.tree_selected).unwrap_or(0);
+        (index < rows.len()).then(|| rows.swap_remove(index))
+    }
+
+    /// Moves the panel's selection one process down, or up when not `down`.
+    fn move_tree_selection(&mut self, down: bool) {
+        let rows = self.tree_rows();
+        let Some(current) = self.selected_tree_row().and_then(|row| rows.iter().position(|r| r.pid == row.pid)) else {
+            return;
+        };
+        let next = if down { (current + 1).min(rows.len() - 1) } else { current.saturating_sub(1) };
+        self.tree_selected = Some(rows[next].pid);
+    }
+
+    /// Works out again what each pane shows, after the streams, filter or
+    /// search changed.
+    fn update_views(&mut self) {
+        let view = OutputView { streams: self.stream_filter, filter: self.line_filter.as_ref(), json: Some(&self.config.app.json) };
+        for process in &mut self.processes {
+            process.set_view(view, self.search.as_ref());
+        }
+    }
+
+    /// Pretty-prints the JSON log line at the current search match or,
+    /// without one, the last JSON log line in view of the selected pane.
+    fn expand_json(&mut self) {
+        let visible_height = self.pane_rects()[self.selected].height.saturating_sub(2) as usize;
+        let process = &self.processes[self.selected];
+        let (output, shown) = (&process.output, &process.shown);
+        // The same window draw_output_lines shows
+        let max_scroll_up = shown.len(output).saturating_sub(visible_height);
+        let top = if process.follow_mode { max_scroll_up } else { max_scroll_up.saturating_sub(process.scroll_offset) };
+        let bottom = shown.len(output).min(top + visible_height);
+        let json = &self.config.app.json;
+        let pretty = |index: usize| {
+            let line = output.get(index)?;
+            json.pretty(&line.text)
+        };
+        self.expanded_json = match self.search_match {
+            Some(id) => output.index_of(id).and_then(pretty),
+            None => (top..bottom).rev().find_map(|position| pretty(shown.index(output, position)?)),
+        };
+        self.json_scroll = 0;
+    }
+
+    /// Scrolls the pretty-printed JSON by `lines`, down when `down`, no
+    /// further than its last line reaching the bottom of the pane.
+    fn scroll_json(&mut self, down: bool, lines: usize) {
+        let Some(json) = &self.expanded_json else { return };
+        let rect = self.pane_rects()[self.selected];
+        let (width, height) = (rect.width.saturating_sub(2).max(1) as usize, rect.height.saturating_sub(2) as usize);
+        // Long values wrap onto several lines
+        let total: usize = json.lines().map(|line| line.chars().count().div_ceil(width).max(1)).sum();
+        let max_scroll = total.saturating_sub(height);
+        self.json_scroll = if down { (self.json_scroll + lines).min(max_scroll) } else { self.json_scroll.saturating_sub(lines) };
+    }
+
+    /// Scrolls the selected pane to the next search match down, or up when
+    /// not `forward`, leaving follow mode.
+    fn jump_to_match(&mut self, forward: bool) {
+        if self.search.is_none() {
+            return;
+        }
+        let visible_height = self.pane_rects()[self.selected].height.saturating_sub(2) as usize;
+        let process = &mut self.processes[self.selected];
+        let total_lines = process.shown.len(&process.output);
+        self.search_match = step(process.shown.matches(), self.search_match, forward);
+        let position = self.search_match.and_then(|id| process.shown.position(&process.output, id));
+        if let Some(position) = position {
+            // Put the match in the middle of the pane where possible
+            let max_scroll_up = total_lines.saturating_sub(visible_height);
+            let top = position.saturating_sub(visible_height / 2).min(max_scroll_up);
+            process.follow_mode = false;
+            process.scroll_offset = max_scroll_up - top;
+        }
+    }
+
+    /// Handles a key typed at the search or filter prompt; the output follows
+    /// along as the pattern is typed.
+    fn handle_prompt_key(&mut self, key: &KeyEvent) {
+        let Some(prompt) = &mut self.prompt else { return };
+        let kind = prompt.kind;
+        match prompt.handle_key(key) {
+            PromptEvent::Submitted => self.prompt = None,
+            PromptEvent::Cancelled => {
+                self.prompt = None;
+                match kind {
+                    PromptKind::Search => self.search = None,
+                    PromptKind::Filter => self.line_filter = None,
+                }
+                self.search_match = None;
+                self.update_views();
+            }
+            PromptEvent::Edited => match Search::new(&prompt.input) {
+                Ok(pattern) => {
+                    prompt.invalid = false;
+                    self.search_match = None;
+                    match kind {
+                        PromptKind::Search => {
+                            self.search = pattern;
+                            self.update_views();
+                            // Start from the most recent match
+                            self.jump_to_match(false);
+                        }
+                        PromptKind::Filter => {
+                            let invert = self.line_filter.as_ref().is_some_and(|filter| filter.invert);
+                            self.line_filter = pattern.map(|search| LineFilter { search, invert });
+                            self.update_views();
+                        }
+                    }
+                }
+                Err(_) => prompt.invalid = true,
+            },
+        }
+    }
+
+    /// Handles a key press, returning true when druns should exit.
+    async fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if self.prompt.is_some() {
+            self.handle_prompt_key(&key);
+            return false;
+        }
+        if let Some(pending) = self.pending_signal.take() {
+            // Only y or Enter sends the signal; any other key cancels it
+            if matches!(key.code, KeyCode::Char('y') | KeyCode::Enter) {
+                self.processes[self.selected].signal_descendant(pending.pid, pending.signal, pending.name);
+            }
+            return false;
+        }
+        if self.input_mode {
+            let process = &mut self.processes[self.selected];
+            if self.prefix_pending {
+                self.prefix_pending = false;
+                if self.prefix_key.matches(&key) {
+                    // Pressing the prefix twice sends it through to the child
+                    process.send_key(&key);
+                    return false;
+                }
+                // Any other key leaves input mode and runs as a druns binding
+                self.input_mode = false;
+            } else if self.prefix_key.matches(&key) {
+                self.prefix_pending = true;
+                return false;
+            } else if process.process_manager.mode == OutputMode::Pty || !is_ctrl_c(&key) {
+                // A pipe can't deliver ctrl-c as SIGINT, so that stays with druns
+                process.send_key(&key);
+                return false;
+            }
+        }
+
+        let pane_height = self.pane_rects()[self.selected].height;
+        let page_height = (pane_height.saturating_sub(4) as usize).min(20);
+        let process = &mut self.processes[self.selected];
+        let total_lines = process.shown.len(&process.output);
+        let visible_height = page_height;
+        let max_scroll_up = total_lines.saturating_sub(visible_height);
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                if !self.processes.iter().any(SupervisedProcess::is_running) {
+                    // Nothing left to stop, e.g. while waiting for changes
+                    return true;
+                }
+                self.quit();
+                for process in &mut self.processes {
+                    process.request_stop(self.grace_period).await;
+                }
+            }
+            KeyCode::Char('r') => {
+                // Stop the selected command and run it again
+                process.request_restart("restart requested".to_string(), true, self.grace_period).await;
+            }
+            _ if self.prefix_key.matches(&key) => {
+                self.input_mode = true;
+            }
+            KeyCode::Char('i') => {
+                // Send keys to the selected process until the prefix key is pressed
+                self.input_mode = true;
+            }
+            KeyCode::Tab => {
+                self.select((self.selected + 1) % self.processes.len());
+            }
+            KeyCode::BackTab => {
+                self.select((self.selected + self.processes.len() - 1) % self.processes.len());
+            }
+            KeyCode::Char(digit @ '1'..='9') => {
+                let index = digit as usize - '1' as usize;
+                if index < self.processes.len() {
+                    self.select(index);
+                }
+            }
+            KeyCode::Char('/') => {
+                // Search the output, with the matches following along as the pattern is typed
+                self.prompt = Some(Prompt::new(PromptKind::Search));
+            }
+            KeyCode::Char('&') => {
+                // Show only the lines matching a pattern, narrowing as it's typed
+                self.prompt = Some(Prompt::new(PromptKind::Filter));
+            }
+            KeyCode::Char('!') => {
+                // Show the lines the filter leaves out instead
+                if let Some(filter) = &mut self.line_filter {
+                    filter.invert = !filter.invert;
+                    self.search_match = None;
+                    self.update_views();
+                }
+            }
+            KeyCode::Char('n') => {
+                self.jump_to_match(true);
+            }
+            KeyCode::Char('N') => {
+                self.jump_to_match(false);
+            }
+            KeyCode::Char('v') => {
+                // Switch between tabs and split panes
+                self.pane_layout = match self.pane_layout {
+                    PaneLayout::Tabs => PaneLayout::Split,
+                    PaneLayout::Split => PaneLayout::Tabs,
+                };
+            }
+            KeyCode::Char('s') => {
+                // Show both streams, then only stdout, then only stderr
+                self.stream_filter = self.stream_filter.next();
+                self.search_match = None;
+                self.update_views();
+            }
+            KeyCode::Char('e') => {
+                // Show the JSON log line in view pretty-printed, or go back to the output
+                match self.expanded_json {
+                    Some(_) => self.expanded_json = None,
+                    None => {
+                        self.show_tree = false;
+                        self.expand_json();
+                    }
+                }
+            }
+            KeyCode::Char('p') => {
+                // List every process the command started, with its own usage
+                self.show_tree = !self.show_tree;
+                self.expanded_json = None;
+            }
+            KeyCode::Char('o') if self.show_tree => {
+                // Sort the process list by the next column
+                self.tree_sort = self.tree_sort.next();
+            }
+            KeyCode::Up if self.show_tree => {
+                self.move_tree_selection(false);
+            }
+            KeyCode::Down if self.show_tree => {
+                self.move_tree_selection(true);
+            }
+            KeyCode::Char(key) if self.show_tree && PANEL_SIGNALS.iter().any(|&(k, _, _)| k == key) => {
+                // Signal just the selected process, once confirmed
+                let (_, signal, name) = PANEL_SIGNALS.into_iter().find(|&(k, _, _)| k == key).expect("checked above");
+                if let Some(row) = self.selected_tree_row() {
+                    self.pending_signal = Some(PendingSignal { pid: row.pid, command: row.command, signal, name });
+                }
+            }
+            KeyCode::Char('t') => {
+                // Cycle the timestamp gutter: off, time of day, since start, since the previous line
+                self.timestamps = self.timestamps.next();
+            }
+            KeyCode::Char('f') => {
+                // Toggle follow mode
+                process.follow_mode = !process.follow_mode;
+                if process.follow_mode {
+                    process.scroll_offset = 0;
+                }
+            }
+            KeyCode::Up | KeyCode::Down | KeyCode::PageUp | KeyCode::PageDown if self.expanded_json.is_some() => {
+                // Scroll the pretty-printed JSON instead of the output
+                let lines = if matches!(key.code, KeyCode::PageUp | KeyCode::PageDown) { page_height } else { 1 };
+                self.scroll_json(matches!(key.code, KeyCode::Down | KeyCode::PageDown), lines);
+            }
+            KeyCode::Up => {
+                // Scroll up by one line
+                if process.follow_mode {
+                    process.follow_mode = false;
+                }
+                process.scroll_offset += 1;
+            }
+            KeyCode::Down => {
+                // Scroll down by one line
+                process.scroll_offset = process.scroll_offset.saturating_sub(1);
+                if process.scroll_offset == 0 {
+                    process.follow_mode = true;
+                }
+            }
+            KeyCode::PageUp => {
+                // Scroll up by page height
+                if process.follow_mode {
+                    process.follow_mode = false;
+                }
+                process.scroll_offset += page_height;
+            }
+            KeyCode::PageDown => {
+                // Scroll down by page height
+                process.scroll_offset = process.scroll_offset.saturating_sub(page_height);
+                if process.scroll_offset == 0 {
+                    process.follow_mode = true;
+                }
+            }
+            KeyCode::Home => {
+                // Scroll to top
+                process.follow_mode = false;
+                process.scroll_offset = max_scroll_up;
+            }
+            KeyCode::End => {
+                // Scroll to bottom and enable follow mode
+                process.follow_mode = true;
+                process.scroll_offset = 0;
+            }
+            KeyCode::Esc if self.expanded_json.is_some() || self.show_tree => {
+                self.expanded_json = None;
+                self.show_tree = false;
+            }
+            KeyCode::Esc => {
+                // Return to end of log and enable follow mode
+                process.follow_mode = true;
+                process.scroll_offset = 0;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Restores the terminal, waits for every process and prints the end of
+    /// their output. Returns the first failing status, or success; a timeout
+    /// exits with `TIMEOUT_EXIT_CODE` so scripts can tell it from a failure.
     pub async fn cleanup(&mut self) -> Result<std::process::ExitStatus, Box<dyn std::error::Error>> {
         // Cleanup
         disable_raw_mode()?;
         execute!(self.terminal.backend_mut(), LeaveAlternateScreen)?;
         self.terminal.show_cursor()?;
-        let status = self.process_manager.wait().await?;
+        let mut statuses = Vec::new();
+        for process in &mut self.processes {
+            statuses.push(process.process_manager.wait().await?);
+            // Don't leave background jobs from the command running after druns exits
+            process.process_manager.stop_leftovers(self.grace_period).await;
+            process.drain_output();
+        }
 
         // Print last few lines of output
-        let last_lines = self.output_lines.iter().rev().take(10).collect::<Vec<_>>().into_iter().rev();
-        for line in last_lines {
-            println!("{line}");
+        let multiple = self.processes.len() > 1;
+        for process in &self.processes {
+            for line in process.output.tail(10) {
+                let repeats = if line.repeats > 0 { format!(" ×{}", line.repeats + 1) } else { String::new() };
+                if multiple {
+                    println!("{} | {}{repeats}", process.name, line.text);
+                } else {
+                    println!("{}{repeats}", line.text);
+                }
+            }
         }
         println!("Thank you for using druns!");
 
-        Ok(status)
+        if self.processes.iter().any(|process| process.timed_out) {
+            return Ok(ExitStatus::from_raw(TIMEOUT_EXIT_CODE << 8));
+        }
+        let status = statuses.iter().find(|status| !status.success()).or(statuses.first());
+        Ok(*status.expect("at least one process"))
+    }
+}
+
+/// The commands of a multi-process run: the `--procfile`, or the config's
+/// `[[process]]` tables when no command was given.
+fn process_definitions(
+    args: &Args,
+    command: &[String],
+    config: &Config,
+) -> Result<Vec<ProcessDefinition>, Box<dyn std::error::Error>> {
+    if let Some(path) = &args.procfile {
+        if !command.is_empty() {
+            return Err("--procfile can't be combined with a command".into());
+        }
+        return procfile::load(path);
+    }
+    if !command.is_empty() {
+        return Ok(Vec::new());
+    }
+    if config.processes.is_empty() {
+        return Err("No command provided, and the config file has no [[process]] tables".into());
+    }
+    for definition in &config.processes {
+        procfile::validate(definition)?;
+    }
+    procfile::check_unique(&config.processes)?;
+    Ok(config.processes.clone())
+}
+
+/// Waits for the next changed path, or forever when not watching.
+async fn recv_change(rx: &mut Option<mpsc::UnboundedReceiver<PathBuf>>) -> Option<PathBuf> {
+    match rx {
+        Some(rx) => rx.recv().await,
+        None => std::future::pending().await,
     }
 }
 
+fn is_ctrl_c(key: &KeyEvent) -> bool {
+    key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::cli::Args;
+    use crate::terminal::ScreenMode;
 
     // Mock Args for testing
     fn create_test_args() -> Args {
@@ -439,9 +1015,31 @@ mod tests {
             config: None,
             sidebar_width: 30,
             max_output_lines: 1000,
+            unlimited_scrollback: false,
             max_command_lines: 3,
             animation: true,
             no_animate: false,
+            pipes: false,
+            log_raw: false,
+            log_stderr: None,
+            log_timestamps: false,
+            timestamps: None,
+            dedup: None,
+            highlights: vec![],
+            screen: ScreenMode::Auto,
+            interactive: false,
+            prefix_key: None,
+            shell: false,
+            grace_period: None,
+            restart: None,
+            max_restarts: None,
+            timeout: None,
+            network_device: None,
+            watch: vec![],
+            ignore: vec![],
+            debounce: None,
+            procfile: None,
+            layout: None,
         }
     }
 
@@ -471,9 +1069,31 @@ mod tests {
             config: None,
             sidebar_width: 50,
             max_output_lines: 2000,
+            unlimited_scrollback: false,
             max_command_lines: 5,
             animation: false,
             no_animate: true,
+            pipes: false,
+            log_raw: false,
+            log_stderr: None,
+            log_timestamps: false,
+            timestamps: None,
+            dedup: None,
+            highlights: vec![],
+            screen: ScreenMode::Auto,
+            interactive: false,
+            prefix_key: None,
+            shell: false,
+            grace_period: None,
+            restart: None,
+            max_restarts: None,
+            timeout: None,
+            network_device: None,
+            watch: vec![],
+            ignore: vec![],
+            debounce: None,
+            procfile: None,
+            layout: None,
         };
 
         let mut config = Config::with_defaults();
@@ -493,6 +1113,30 @@ mod tests {
         assert!(!config.app.animation.animation_enabled);
     }
 
+    #[test]
+    fn test_process_definitions() {
+        let mut config = Config::with_defaults();
+        config.processes.push(ProcessDefinition { name: "web".to_string(), command: "./web".to_string() });
+
+        // Config tables only apply when there's no command
+        let args = Args { command: vec![], ..create_test_args() };
+        assert_eq!(process_definitions(&args, &[], &config).unwrap(), config.processes);
+        let command = vec!["ls".to_string()];
+        assert!(process_definitions(&args, &command, &config).unwrap().is_empty());
+
+        let args = Args { procfile: Some("Procfile".to_string()), ..create_test_args() };
+        let error = process_definitions(&args, &command, &config).unwrap_err().to_string();
+        assert_eq!(error, "--procfile can't be combined with a command");
+
+        config.processes.push(ProcessDefinition { name: "web".to_string(), command: "./other".to_string() });
+        let args = Args { command: vec![], ..create_test_args() };
+        assert!(process_definitions(&args, &[], &config).is_err());
+
+        // A config file without [[process]] tables is no substitute for a command
+        let error = process_definitions(&args, &[], &Config::with_defaults()).unwrap_err().to_string();
+        assert_eq!(error, "No command provided, and the config file has no [[process]] tables");
+    }
+
     #[test]
     fn test_min_sidebar_width_calculation() {
         let header_content = include_str!("../static/top_art.txt");
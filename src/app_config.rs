@@ -1,8 +1,15 @@
+use crate::restart::RestartLimits;
+use crate::utils::parse_duration;
 use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 pub use crate::ui_layout::LayoutConfig;
 pub use crate::ui_output::OutputConfig;
 pub use crate::ui_animation::AnimationConfig;
+pub use crate::ui_input::InputConfig;
+pub use crate::process_config::ProcessConfig;
+pub use crate::watch_config::WatchConfig;
+pub use crate::json_log::JsonLogConfig;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct AppConfig {
@@ -12,6 +19,14 @@ pub struct AppConfig {
     pub output: OutputConfig,
     #[serde(flatten)]
     pub animation: AnimationConfig,
+    #[serde(flatten)]
+    pub input: InputConfig,
+    #[serde(flatten)]
+    pub process: ProcessConfig,
+    #[serde(flatten)]
+    pub watch: WatchConfig,
+    #[serde(flatten)]
+    pub json: JsonLogConfig,
 }
 
 impl AppConfig {
@@ -71,6 +86,37 @@ impl AppConfig {
     pub fn shine_width_midpoint(&self) -> f32 {
         self.animation.shine_width_midpoint
     }
+
+    pub fn prefix_key(&self) -> &str {
+        &self.input.prefix_key
+    }
+
+    pub fn shell(&self) -> &str {
+        &self.process.shell
+    }
+
+    pub fn grace_period_secs(&self) -> f64 {
+        self.process.grace_period_secs
+    }
+
+    /// The configured run timeout, if any.
+    pub fn timeout(&self) -> Result<Option<Duration>, String> {
+        self.process.timeout.as_deref().map(parse_duration).transpose()
+    }
+
+    pub fn restart_limits(&self) -> RestartLimits {
+        RestartLimits {
+            max_restarts: self.process.max_restarts,
+            backoff: Duration::from_millis(self.process.restart_backoff_ms),
+            backoff_max: Duration::from_millis(self.process.restart_backoff_max_ms),
+            crash_loop_restarts: self.process.crash_loop_restarts,
+            crash_loop_window: Duration::from_secs(self.process.crash_loop_window_secs),
+        }
+    }
+
+    pub fn watch_debounce_ms(&self) -> u64 {
+        self.watch.watch_debounce_ms
+    }
 }
 
 #[cfg(test)]
@@ -120,6 +166,22 @@ mod tests {
         assert_eq!(config.animation_enabled(), config.animation.animation_enabled);
         assert_eq!(config.no_animate(), config.animation.no_animate);
         assert_eq!(config.max_command_lines(), config.layout.max_command_lines);
+        assert_eq!(config.prefix_key(), config.input.prefix_key);
+        assert_eq!(config.shell(), config.process.shell);
+        assert_eq!(config.grace_period_secs(), config.process.grace_period_secs);
+        assert_eq!(config.restart_limits().backoff, Duration::from_millis(config.process.restart_backoff_ms));
+        assert_eq!(config.restart_limits().max_restarts, config.process.max_restarts);
+        assert_eq!(config.timeout(), Ok(None));
+        assert_eq!(config.watch_debounce_ms(), config.watch.watch_debounce_ms);
+    }
+
+    #[test]
+    fn test_timeout_parsing() {
+        let mut config = AppConfig::default();
+        config.process.timeout = Some("10m".to_string());
+        assert_eq!(config.timeout(), Ok(Some(Duration::from_secs(600))));
+        config.process.timeout = Some("soon".to_string());
+        assert!(config.timeout().is_err());
     }
 
     #[test]
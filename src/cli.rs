@@ -1,4 +1,10 @@
+use crate::restart::RestartPolicy;
+use crate::terminal::ScreenMode;
+use crate::utils::parse_duration;
+use crate::ui_layout::PaneLayout;
+use crate::ui_output::{DedupMode, TimestampMode};
 use clap::Parser;
+use std::time::Duration;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -11,6 +17,30 @@ pub struct Args {
     #[arg(long)]
     pub log: Option<String>,
 
+    /// Keep color and other escape sequences in the --log file instead of stripping them
+    #[arg(long)]
+    pub log_raw: bool,
+
+    /// Log STDERR to this file instead of the --log file
+    #[arg(long, value_name = "PATH")]
+    pub log_stderr: Option<String>,
+
+    /// Start each line in the log files with the time it arrived
+    #[arg(long)]
+    pub log_timestamps: bool,
+
+    /// Show when each output line arrived in a gutter (`t` cycles through the modes)
+    #[arg(long, value_name = "MODE")]
+    pub timestamps: Option<TimestampMode>,
+
+    /// Collapse runs of repeated lines into one with a count; the log files keep every line
+    #[arg(long, value_name = "MODE")]
+    pub dedup: Option<DedupMode>,
+
+    /// Load `[[highlight]]` rules from this file, after the config's own (repeatable)
+    #[arg(long, value_name = "PATH")]
+    pub highlights: Vec<String>,
+
     /// Configuration file path
     #[arg(long)]
     pub config: Option<String>,
@@ -23,6 +53,10 @@ pub struct Args {
     #[arg(long, default_value = "1000")]
     pub max_output_lines: usize,
 
+    /// Page output past --max-output-lines out to a temporary file so it can all be scrolled back through
+    #[arg(long)]
+    pub unlimited_scrollback: bool,
+
     /// Maximum number of lines to display for the command
     #[arg(long, default_value = "3")]
     pub max_command_lines: usize,
@@ -34,6 +68,66 @@ pub struct Args {
     /// Disable animation (equivalent to --animation false)
     #[arg(long)]
     pub no_animate: bool,
+
+    /// Capture output through pipes instead of running the command under a pseudo-terminal
+    #[arg(long)]
+    pub pipes: bool,
+
+    /// When to draw the emulated terminal screen instead of the scrolling output
+    #[arg(long, value_enum, default_value_t = ScreenMode::Auto)]
+    pub screen: ScreenMode,
+
+    /// Start with the keyboard connected to the command (input mode)
+    #[arg(long)]
+    pub interactive: bool,
+
+    /// Key that leaves input mode, e.g. "ctrl-]" (overrides the config file)
+    #[arg(long)]
+    pub prefix_key: Option<String>,
+
+    /// Run the command through the shell as a single string instead of executing it directly
+    #[arg(long)]
+    pub shell: bool,
+
+    /// Seconds to wait after SIGTERM before SIGKILLing the command (overrides the config file)
+    #[arg(long, value_name = "SECS")]
+    pub grace_period: Option<f64>,
+
+    /// Start the command again when it exits on its own (overrides the config file)
+    #[arg(long, value_enum, value_name = "POLICY")]
+    pub restart: Option<RestartPolicy>,
+
+    /// Give up restarting automatically after this many restarts (overrides the config file)
+    #[arg(long, value_name = "N")]
+    pub max_restarts: Option<u32>,
+
+    /// Stop the command if a run takes longer than this, e.g. 30s, 10m or 1h30m (overrides the config file)
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub timeout: Option<Duration>,
+
+    /// Interface to capture the command's traffic on with experimental-pcap, or "any" (overrides the config file)
+    #[arg(long, value_name = "NAME")]
+    pub network_device: Option<String>,
+
+    /// Rerun the command when files under this path change (repeatable)
+    #[arg(long, value_name = "PATH")]
+    pub watch: Vec<String>,
+
+    /// Gitignore-style pattern for changes that shouldn't trigger a rerun (repeatable)
+    #[arg(long, value_name = "GLOB")]
+    pub ignore: Vec<String>,
+
+    /// Milliseconds to wait for changes to settle before rerunning (overrides the config file)
+    #[arg(long, value_name = "MS")]
+    pub debounce: Option<u64>,
+
+    /// Run the processes listed in a Procfile side by side instead of a single command
+    #[arg(long, value_name = "PATH", num_args = 0..=1, default_missing_value = "Procfile")]
+    pub procfile: Option<String>,
+
+    /// How to show the output of several processes (overrides the config file)
+    #[arg(long, value_enum)]
+    pub layout: Option<PaneLayout>,
 }
 
 #[cfg(test)]
@@ -48,7 +142,26 @@ mod tests {
         assert_eq!(args.max_command_lines, 3);
         assert!(args.animation);
         assert!(!args.no_animate);
+        assert!(!args.pipes);
+        assert_eq!(args.screen, ScreenMode::Auto);
+        assert!(!args.interactive);
+        assert!(args.prefix_key.is_none());
+        assert!(!args.shell);
+        assert!(args.grace_period.is_none());
+        assert!(args.restart.is_none());
+        assert!(args.max_restarts.is_none());
+        assert!(args.timeout.is_none());
+        assert!(args.watch.is_empty());
+        assert!(args.ignore.is_empty());
+        assert!(args.debounce.is_none());
+        assert!(args.procfile.is_none());
+        assert!(args.layout.is_none());
         assert!(args.log.is_none());
+        assert!(!args.log_raw);
+        assert!(args.log_stderr.is_none());
+        assert!(!args.log_timestamps);
+        assert!(args.timestamps.is_none());
+        assert!(args.highlights.is_empty());
         assert!(args.config.is_none());
         assert!(args.command.is_empty());
     }
@@ -69,6 +182,8 @@ mod tests {
     fn test_max_output_lines() {
         let args = Args::parse_from(["test", "--max-output-lines", "500"]);
         assert_eq!(args.max_output_lines, 500);
+        assert!(!args.unlimited_scrollback);
+        assert!(Args::parse_from(["test", "--unlimited-scrollback", "cmd"]).unlimited_scrollback);
     }
 
     #[test]
@@ -90,12 +205,124 @@ mod tests {
         assert!(!args.no_animate);
     }
 
+    #[test]
+    fn test_pipes_flag() {
+        let args = Args::parse_from(["test", "--pipes", "cargo", "build"]);
+        assert!(args.pipes);
+        assert_eq!(args.command, vec!["cargo", "build"]);
+    }
+
+    #[test]
+    fn test_screen_option() {
+        let args = Args::parse_from(["test", "--screen", "always", "htop"]);
+        assert_eq!(args.screen, ScreenMode::Always);
+        assert!(Args::try_parse_from(["test", "--screen", "sometimes"]).is_err());
+    }
+
+    #[test]
+    fn test_interactive_options() {
+        let args = Args::parse_from(["test", "--interactive", "--prefix-key", "ctrl-b", "npm", "init"]);
+        assert!(args.interactive);
+        assert_eq!(args.prefix_key, Some("ctrl-b".to_string()));
+        assert_eq!(args.command, vec!["npm", "init"]);
+    }
+
+    #[test]
+    fn test_shell_option() {
+        let args = Args::parse_from(["test", "--shell", "ls *.rs | wc -l"]);
+        assert!(args.shell);
+        assert_eq!(args.command, vec!["ls *.rs | wc -l"]);
+    }
+
+    #[test]
+    fn test_grace_period_option() {
+        let args = Args::parse_from(["test", "--grace-period", "0.5", "docker", "compose", "up"]);
+        assert_eq!(args.grace_period, Some(0.5));
+        assert_eq!(args.command, vec!["docker", "compose", "up"]);
+    }
+
+    #[test]
+    fn test_restart_options() {
+        let args = Args::parse_from(["test", "--restart", "on-failure", "--max-restarts", "3", "./server"]);
+        assert_eq!(args.restart, Some(RestartPolicy::OnFailure));
+        assert_eq!(args.max_restarts, Some(3));
+        assert_eq!(args.command, vec!["./server"]);
+        assert!(Args::try_parse_from(["test", "--restart", "sometimes"]).is_err());
+    }
+
+    #[test]
+    fn test_timeout_option() {
+        let args = Args::parse_from(["test", "--timeout", "10m", "./integration.sh"]);
+        assert_eq!(args.timeout, Some(Duration::from_secs(600)));
+        assert!(Args::try_parse_from(["test", "--timeout", "forever", "./integration.sh"]).is_err());
+    }
+
+    #[test]
+    fn test_watch_options() {
+        let args = Args::parse_from([
+            "test", "--watch", "src", "--watch", "Cargo.toml", "--ignore", "*.snap", "--debounce", "100", "cargo", "test",
+        ]);
+        assert_eq!(args.watch, vec!["src", "Cargo.toml"]);
+        assert_eq!(args.ignore, vec!["*.snap"]);
+        assert_eq!(args.debounce, Some(100));
+        assert_eq!(args.command, vec!["cargo", "test"]);
+    }
+
+    #[test]
+    fn test_procfile_options() {
+        let args = Args::parse_from(["test", "--procfile"]);
+        assert_eq!(args.procfile, Some("Procfile".to_string()));
+        assert!(args.command.is_empty());
+
+        let args = Args::parse_from(["test", "--layout", "split", "--procfile", "Procfile.dev"]);
+        assert_eq!(args.procfile, Some("Procfile.dev".to_string()));
+        assert_eq!(args.layout, Some(PaneLayout::Split));
+        assert!(Args::try_parse_from(["test", "--layout", "grid"]).is_err());
+    }
+
     #[test]
     fn test_log_option() {
         let args = Args::parse_from(["test", "--log", "output.log"]);
         assert_eq!(args.log, Some("output.log".to_string()));
     }
 
+    #[test]
+    fn test_log_raw_option() {
+        let args = Args::parse_from(["test", "--log", "output.log", "--log-raw"]);
+        assert_eq!(args.log, Some("output.log".to_string()));
+        assert!(args.log_raw);
+    }
+
+    #[test]
+    fn test_timestamp_options() {
+        let args = Args::parse_from(["test", "--timestamps", "delta", "--log", "out.log", "--log-timestamps", "cmd"]);
+        assert_eq!(args.timestamps, Some(TimestampMode::Delta));
+        assert!(args.log_timestamps);
+        assert!(Args::try_parse_from(["test", "--timestamps", "relative"]).is_err());
+    }
+
+    #[test]
+    fn test_dedup_option() {
+        let args = Args::parse_from(["test", "cmd"]);
+        assert!(args.dedup.is_none());
+        let args = Args::parse_from(["test", "--dedup", "numbers", "cmd"]);
+        assert_eq!(args.dedup, Some(DedupMode::Numbers));
+        assert!(Args::try_parse_from(["test", "--dedup", "fuzzy", "cmd"]).is_err());
+    }
+
+    #[test]
+    fn test_highlights_option_repeats() {
+        let args = Args::parse_from(["test", "--highlights", "team.toml", "--highlights", "mine.toml", "cmd"]);
+        assert_eq!(args.highlights, vec!["team.toml", "mine.toml"]);
+    }
+
+    #[test]
+    fn test_log_stderr_option() {
+        let args = Args::parse_from(["test", "--log", "out.log", "--log-stderr", "err.log", "cmd"]);
+        assert_eq!(args.log, Some("out.log".to_string()));
+        assert_eq!(args.log_stderr, Some("err.log".to_string()));
+    }
+
     #[test]
     fn test_config_option() {
         let args = Args::parse_from(["test", "--config", "config.toml"]);
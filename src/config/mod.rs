@@ -2,6 +2,8 @@
 mod tests;
 
 use crate::app_config::AppConfig;
+use crate::highlight::HighlightRule;
+use crate::procfile::ProcessDefinition;
 use crate::theme::Theme;
 use serde::{Deserialize, Serialize};
 use std::path::Path;
@@ -10,6 +12,12 @@ use std::path::Path;
 pub struct Config {
     pub app: AppConfig,
     pub theme: Theme,
+    /// `[[process]]` tables: commands to run side by side, like a Procfile
+    #[serde(default, rename = "process", skip_serializing_if = "Vec::is_empty")]
+    pub processes: Vec<ProcessDefinition>,
+    /// `[[highlight]]` tables: styles for output matching a pattern
+    #[serde(default, rename = "highlight", skip_serializing_if = "Vec::is_empty")]
+    pub highlights: Vec<HighlightRule>,
 }
 
 impl Config {
@@ -64,6 +64,15 @@ fn test_config_serialization_with_custom_values() {
     assert_eq!(deserialized.app.output.max_output_lines, 1500);
 }
 
+#[test]
+fn test_config_prefix_key_is_optional() {
+    let config = Config::default();
+    let mut toml = toml::to_string(&config).unwrap();
+    toml = toml.replace("prefix_key = \"ctrl-]\"\n", "");
+    let deserialized: Config = toml::from_str(&toml).unwrap();
+    assert_eq!(deserialized.app.input.prefix_key, "ctrl-]");
+}
+
 #[test]
 fn test_config_load_from_nonexistent_file() {
     let result = Config::load_from_file("nonexistent_config.toml");
@@ -172,4 +181,47 @@ fn test_config_with_defaults_method() {
 
         let reloaded = Config::load_from_file(&file_path).unwrap();
         assert_eq!(reloaded.app.layout.sidebar_width, original.app.layout.sidebar_width);
-    }
\ No newline at end of file
+    }
+
+#[test]
+fn test_process_tables() {
+    let mut config = Config::default();
+    config.processes.push(ProcessDefinition { name: "web".to_string(), command: "./web --port 3000".to_string() });
+    config.processes.push(ProcessDefinition { name: "worker".to_string(), command: "./worker".to_string() });
+    let toml = toml::to_string(&config).unwrap();
+    assert!(toml.contains("[[process]]"));
+
+    let deserialized: Config = toml::from_str(&toml).unwrap();
+    assert_eq!(deserialized.processes, config.processes);
+}
+
+#[test]
+fn test_no_process_tables_by_default() {
+    let toml = toml::to_string(&Config::default()).unwrap();
+    assert!(!toml.contains("[[process]]"));
+    let deserialized: Config = toml::from_str(&toml).unwrap();
+    assert!(deserialized.processes.is_empty());
+}
+
+#[test]
+fn test_highlight_tables() {
+    let mut config = Config::default();
+    config.highlights.push(HighlightRule { pattern: "error|panicked".to_string(), style: "red bold".to_string(), group: None });
+    let toml = toml::to_string(&config).unwrap();
+    assert!(toml.contains("[[highlight]]"));
+
+    let deserialized: Config = toml::from_str(&toml).unwrap();
+    assert_eq!(deserialized.highlights, config.highlights);
+}
+
+#[test]
+fn test_json_log_fields() {
+    let mut config = Config::default();
+    assert!(config.app.json.json_logs);
+    config.app.json.json_message_fields = vec!["event".to_string()];
+    let toml = toml::to_string(&config).unwrap();
+    assert!(toml.contains("json_message_fields = [\"event\"]"));
+
+    let deserialized: Config = toml::from_str(&toml).unwrap();
+    assert_eq!(deserialized.app.json.json_message_fields, vec!["event"]);
+}
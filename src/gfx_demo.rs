@@ -134,7 +134,7 @@ impl GfxDemoApp {
                     self.iteration_count += 1;
 
                     // Add new status update every 10 iterations (500ms)
-                    if self.iteration_count % 10 == 0 {
+                    if self.iteration_count.is_multiple_of(10) {
                         let update = generate_status_update();
                         self.status_updates.push(update);
                         // Keep only last 50 updates for scrolling
@@ -181,7 +181,7 @@ impl GfxDemoApp {
                         self.animation_frame = (self.animation_frame + 1) % 110;
                     } else {
                         // Print text output every second
-                        if self.iteration_count % 50 == 0 {
+                        if self.iteration_count.is_multiple_of(50) {
                             println!("GFX Demo - Elapsed: {:.2}s, States: {:.1}, {:.1}, {:.1}, ...",
                                 self.start_time.elapsed().as_secs_f64(),
                                 self.display_states[0].0,
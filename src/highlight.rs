@@ -0,0 +1,219 @@
+use crate::ansi::StyledLine;
+use crate::theme::Theme;
+use ratatui::style::{Color, Modifier, Style};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A capture group, by number or name.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum CaptureGroup {
+    Index(usize),
+    Name(String),
+}
+
+/// A `[[highlight]]` table: output matching `pattern` is drawn in `style`.
+///
+/// `style` is a list of words: a color for the text, `on` and a color for the
+/// background, and any of `bold`, `dim`, `italic`, `underlined`, `reversed`
+/// and `crossed-out`. Colors are names (`red`, `light-blue`), `#rrggbb`, a
+/// 256-color index, or a theme color (`primary`, `accent`, `error`, ...).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HighlightRule {
+    pub pattern: String,
+    pub style: String,
+    /// Style only this capture group instead of the whole match
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<CaptureGroup>,
+}
+
+/// A file of `[[highlight]]` tables on their own, so a project can share its rules.
+#[derive(Debug, Default, Deserialize)]
+struct HighlightFile {
+    #[serde(default, rename = "highlight")]
+    rules: Vec<HighlightRule>,
+}
+
+/// Reads the `[[highlight]]` tables from a file.
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<HighlightRule>, String> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {e}", path.display()))?;
+    let file: HighlightFile = toml::from_str(&contents).map_err(|e| format!("{}: {e}", path.display()))?;
+    Ok(file.rules)
+}
+
+fn theme_color(name: &str, theme: &Theme) -> Option<Color> {
+    match name {
+        "primary" => Some(theme.primary),
+        "secondary" => Some(theme.secondary),
+        "accent" => Some(theme.accent),
+        "border" => Some(theme.border),
+        "text" => Some(theme.text),
+        "background" => Some(theme.background),
+        "error" => Some(theme.error),
+        _ => None,
+    }
+}
+
+fn parse_color(name: &str, theme: &Theme) -> Result<Color, String> {
+    theme_color(name, theme)
+        .or_else(|| name.parse().ok())
+        .ok_or_else(|| format!("unknown color '{name}'"))
+}
+
+/// Turns a rule's style words into a `Style`.
+pub fn parse_style(spec: &str, theme: &Theme) -> Result<Style, String> {
+    let mut style = Style::default();
+    let mut words = spec.split_whitespace();
+    while let Some(word) = words.next() {
+        let word = word.to_lowercase();
+        let modifier = match word.as_str() {
+            "bold" => Modifier::BOLD,
+            "dim" => Modifier::DIM,
+            "italic" => Modifier::ITALIC,
+            "underline" | "underlined" => Modifier::UNDERLINED,
+            "reverse" | "reversed" => Modifier::REVERSED,
+            "crossed-out" | "strikethrough" => Modifier::CROSSED_OUT,
+            "on" => {
+                let color = words.next().ok_or("'on' needs a background color")?;
+                style = style.bg(parse_color(&color.to_lowercase(), theme)?);
+                continue;
+            }
+            color => {
+                style = style.fg(parse_color(color, theme)?);
+                continue;
+            }
+        };
+        style = style.add_modifier(modifier);
+    }
+    Ok(style)
+}
+
+struct CompiledRule {
+    regex: Regex,
+    style: Style,
+    group: Option<CaptureGroup>,
+}
+
+/// The highlight rules, compiled against the theme.
+#[derive(Default)]
+pub struct Highlighter {
+    rules: Vec<CompiledRule>,
+}
+
+impl Highlighter {
+    pub fn new(rules: &[HighlightRule], theme: &Theme) -> Result<Self, String> {
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                let invalid = |e: String| format!("Invalid highlight rule '{}': {e}", rule.pattern);
+                let regex = Regex::new(&rule.pattern).map_err(|e| invalid(e.to_string()))?;
+                let style = parse_style(&rule.style, theme).map_err(invalid)?;
+                match &rule.group {
+                    Some(CaptureGroup::Index(index)) if *index >= regex.captures_len() => {
+                        return Err(invalid(format!("no capture group {index}")));
+                    }
+                    Some(CaptureGroup::Name(name)) if !regex.capture_names().any(|n| n == Some(name)) => {
+                        return Err(invalid(format!("no capture group named '{name}'")));
+                    }
+                    _ => {}
+                }
+                Ok(CompiledRule { regex, style, group: rule.group.clone() })
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Highlighter { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Styles the parts of `line` the rules match. Later rules are drawn over
+    /// earlier ones, and all of them over the program's own colors.
+    pub fn apply(&self, line: &mut StyledLine) {
+        for rule in &self.rules {
+            let ranges: Vec<_> = match &rule.group {
+                None => rule.regex.find_iter(&line.text).map(|found| found.range()).collect(),
+                Some(group) => rule
+                    .regex
+                    .captures_iter(&line.text)
+                    .filter_map(|captures| match group {
+                        CaptureGroup::Index(index) => captures.get(*index),
+                        CaptureGroup::Name(name) => captures.name(name),
+                    })
+                    .map(|found| found.range())
+                    .collect(),
+            };
+            for range in ranges {
+                line.highlight(range.start, range.end, rule.style);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(pattern: &str, style: &str, group: Option<CaptureGroup>) -> HighlightRule {
+        HighlightRule { pattern: pattern.to_string(), style: style.to_string(), group }
+    }
+
+    fn styled_parts(line: &StyledLine) -> Vec<(&str, Style)> {
+        line.runs.iter().map(|run| (&line.text[run.start..run.end], run.style)).collect()
+    }
+
+    #[test]
+    fn test_parse_style() {
+        let theme = Theme::default();
+        assert_eq!(
+            parse_style("red bold", &theme).unwrap(),
+            Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)
+        );
+        assert_eq!(
+            parse_style("black on #ffcc00", &theme).unwrap(),
+            Style::default().fg(Color::Black).bg(Color::Rgb(255, 204, 0))
+        );
+        assert_eq!(parse_style("accent underlined", &theme).unwrap().fg, Some(theme.accent));
+        assert!(parse_style("shiny", &theme).is_err());
+        assert!(parse_style("red on", &theme).is_err());
+    }
+
+    #[test]
+    fn test_whole_match_and_capture_group() {
+        let rules = [
+            rule("error|panicked", "red bold", None),
+            rule(r"\[(?P<service>\w+)\]", "cyan", Some(CaptureGroup::Name("service".to_string()))),
+        ];
+        let highlighter = Highlighter::new(&rules, &Theme::default()).unwrap();
+        let mut line = StyledLine::plain("[api] error: boom");
+        highlighter.apply(&mut line);
+        assert_eq!(
+            styled_parts(&line),
+            vec![
+                ("api", Style::default().fg(Color::Cyan)),
+                ("error", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_rules_are_reported() {
+        let theme = Theme::default();
+        assert!(Highlighter::new(&[rule("(", "red", None)], &theme).is_err());
+        assert!(Highlighter::new(&[rule("a(b)", "red", Some(CaptureGroup::Index(2)))], &theme).is_err());
+        let error = Highlighter::new(&[rule("x", "glowing", None)], &theme).err().unwrap();
+        assert_eq!(error, "Invalid highlight rule 'x': unknown color 'glowing'");
+    }
+
+    #[test]
+    fn test_load_rules_from_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("highlights.toml");
+        std::fs::write(&path, "[[highlight]]\npattern = 'https?://\\S+'\nstyle = 'underlined'\n\n[[highlight]]\npattern = 'took (\\d+)ms'\nstyle = 'yellow'\ngroup = 1\n").unwrap();
+        let rules = load(&path).unwrap();
+        assert_eq!(rules.len(), 2);
+        assert_eq!(rules[1].group, Some(CaptureGroup::Index(1)));
+    }
+}
@@ -0,0 +1,256 @@
+use crate::ansi::StyledLine;
+use crate::theme::Theme;
+use chrono::{DateTime, Local, TimeZone};
+use ratatui::style::{Color, Modifier, Style};
+use serde_json::{Map, Value};
+use std::borrow::Cow;
+
+fn default_json_logs() -> bool {
+    true
+}
+
+fn names(names: &[&str]) -> Vec<String> {
+    names.iter().map(|name| name.to_string()).collect()
+}
+
+fn default_json_time_fields() -> Vec<String> {
+    names(&["time", "timestamp", "ts", "@timestamp"])
+}
+
+fn default_json_level_fields() -> Vec<String> {
+    names(&["level", "lvl", "severity"])
+}
+
+fn default_json_target_fields() -> Vec<String> {
+    names(&["target", "logger", "module"])
+}
+
+fn default_json_message_fields() -> Vec<String> {
+    names(&["msg", "message"])
+}
+
+/// How output lines holding a JSON object are shown. Each field list names
+/// the keys to look for, in order; the first one a line has is used.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct JsonLogConfig {
+    /// Show JSON log lines as `time level target message key=value`
+    #[serde(default = "default_json_logs")]
+    pub json_logs: bool,
+    #[serde(default = "default_json_time_fields")]
+    pub json_time_fields: Vec<String>,
+    #[serde(default = "default_json_level_fields")]
+    pub json_level_fields: Vec<String>,
+    #[serde(default = "default_json_target_fields")]
+    pub json_target_fields: Vec<String>,
+    #[serde(default = "default_json_message_fields")]
+    pub json_message_fields: Vec<String>,
+}
+
+impl Default for JsonLogConfig {
+    fn default() -> Self {
+        JsonLogConfig {
+            json_logs: default_json_logs(),
+            json_time_fields: default_json_time_fields(),
+            json_level_fields: default_json_level_fields(),
+            json_target_fields: default_json_target_fields(),
+            json_message_fields: default_json_message_fields(),
+        }
+    }
+}
+
+impl JsonLogConfig {
+    /// The fields of `text` when the whole line is one JSON object.
+    fn parse(&self, text: &str) -> Option<Map<String, Value>> {
+        let text = text.trim();
+        if !self.json_logs || !text.starts_with('{') {
+            return None;
+        }
+        match serde_json::from_str(text) {
+            Ok(Value::Object(fields)) => Some(fields),
+            _ => None,
+        }
+    }
+
+    /// `line` as `time level target message key=value`, or `None` when it
+    /// isn't a JSON log line.
+    pub fn compact(&self, line: &StyledLine, theme: &Theme) -> Option<StyledLine> {
+        self.build(line, Some(theme))
+    }
+
+    /// `line` as the output pane shows it, without styles: searches and
+    /// filters match this, so they agree with what is highlighted.
+    pub fn shown<'a>(&self, line: &'a StyledLine) -> Cow<'a, StyledLine> {
+        match self.build(line, None) {
+            Some(compact) => Cow::Owned(compact),
+            None => Cow::Borrowed(line),
+        }
+    }
+
+    /// The compact line, styled with `theme` if there is one.
+    fn build(&self, line: &StyledLine, theme: Option<&Theme>) -> Option<StyledLine> {
+        let mut fields = self.parse(&line.text)?;
+        let style = |style: fn(&Theme) -> Style| theme.map_or(Style::default(), style);
+        let mut take = |names: &[String]| names.iter().find_map(|name| fields.shift_remove(name));
+        let time = take(&self.json_time_fields);
+        let level = take(&self.json_level_fields);
+        let target = take(&self.json_target_fields);
+        let message = take(&self.json_message_fields);
+
+        let mut compact = StyledLine {
+            stream: line.stream,
+            arrived: line.arrived,
+            repeats: line.repeats,
+            ..StyledLine::default()
+        };
+        if let Some(time) = time {
+            part(&mut compact, &format_time(&time), style(|theme| Style::default().fg(theme.secondary)));
+        }
+        if let Some(level) = level {
+            let level = level_name(&level);
+            let level_style = theme.map_or(Style::default(), |theme| level_style(&level, theme));
+            part(&mut compact, &format!("{:<5}", level.to_uppercase()), level_style);
+        }
+        if let Some(target) = target {
+            part(&mut compact, &format_value(&target), style(|theme| Style::default().fg(theme.accent)));
+        }
+        if let Some(message) = message {
+            let message = match message {
+                Value::String(text) => text.replace(['\n', '\r', '\t'], " "),
+                other => other.to_string(),
+            };
+            part(&mut compact, &message, style(|_| Style::default().add_modifier(Modifier::BOLD)));
+        }
+        for (key, value) in &fields {
+            part(&mut compact, &format!("{key}="), style(|theme| Style::default().fg(theme.secondary)));
+            compact.push(&format_value(value), Style::default());
+        }
+        Some(compact)
+    }
+
+    /// The JSON object in `text`, indented over several lines.
+    pub fn pretty(&self, text: &str) -> Option<String> {
+        let fields = self.parse(text)?;
+        serde_json::to_string_pretty(&Value::Object(fields)).ok()
+    }
+}
+
+/// Adds one part of a compact line, after a space.
+fn part(line: &mut StyledLine, text: &str, style: Style) {
+    if !line.text.is_empty() {
+        line.push(" ", Style::default());
+    }
+    line.push(text, style);
+}
+
+/// Times are shown as time of day, like the timestamp gutter. Numbers are
+/// taken as seconds or, when that large, milliseconds since the epoch.
+fn format_time(value: &Value) -> String {
+    let local = |time: DateTime<Local>| time.format("%H:%M:%S%.3f").to_string();
+    match value {
+        Value::String(text) => DateTime::parse_from_rfc3339(text)
+            .map(|time| local(time.with_timezone(&Local)))
+            .unwrap_or_else(|_| text.clone()),
+        Value::Number(number) => number
+            .as_f64()
+            .map(|seconds| if seconds > 1e11 { seconds } else { seconds * 1000.0 })
+            .and_then(|millis| Local.timestamp_millis_opt(millis as i64).single())
+            .map_or_else(|| number.to_string(), local),
+        other => other.to_string(),
+    }
+}
+
+/// pino and bunyan number their levels; everyone else names them.
+fn level_name(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Number(number) => match number.as_u64() {
+            Some(10) => "trace".to_string(),
+            Some(20) => "debug".to_string(),
+            Some(30) => "info".to_string(),
+            Some(40) => "warn".to_string(),
+            Some(50) => "error".to_string(),
+            Some(60) => "fatal".to_string(),
+            _ => number.to_string(),
+        },
+        other => other.to_string(),
+    }
+}
+
+fn level_style(level: &str, theme: &Theme) -> Style {
+    match level.to_lowercase().as_str() {
+        "fatal" | "panic" | "critical" | "crit" | "alert" | "emerg" | "emergency" | "error" | "err" => {
+            Style::default().fg(theme.error).add_modifier(Modifier::BOLD)
+        }
+        "warn" | "warning" => Style::default().fg(Color::Yellow),
+        "info" | "notice" => Style::default().fg(Color::Green),
+        "debug" => Style::default().fg(Color::Cyan),
+        "trace" => Style::default().fg(Color::DarkGray),
+        _ => Style::default(),
+    }
+}
+
+/// Strings are shown bare unless they need quotes to stay one readable value.
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::String(text) if !text.is_empty() && !text.contains(|c: char| c.is_whitespace() || c == '"' || c == '=') => {
+            text.clone()
+        }
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compact_json_line() {
+        let config = JsonLogConfig::default();
+        let theme = Theme::default();
+        let line = StyledLine::plain(
+            r#"{"level":"warn","msg":"slow query","target":"db","elapsed_ms":812,"sql":"select 1","ok":true}"#,
+        );
+        let compact = config.compact(&line, &theme).unwrap();
+        assert_eq!(compact.text, r#"WARN  db slow query elapsed_ms=812 sql="select 1" ok=true"#);
+        assert_eq!(compact.runs[0].style, Style::default().fg(Color::Yellow));
+
+        assert!(config.compact(&StyledLine::plain("plain text"), &theme).is_none());
+        assert!(config.compact(&StyledLine::plain("{not json"), &theme).is_none());
+        assert!(config.compact(&StyledLine::plain("[1, 2]"), &theme).is_none());
+        let disabled = JsonLogConfig { json_logs: false, ..JsonLogConfig::default() };
+        assert!(disabled.compact(&line, &theme).is_none());
+
+        // Searches see the same text, unstyled
+        let shown = config.shown(&line);
+        assert_eq!(shown.text, compact.text);
+        assert!(shown.runs.iter().all(|run| run.style == Style::default()));
+        assert!(matches!(config.shown(&StyledLine::plain("plain text")), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_configured_fields_and_numeric_levels() {
+        let config = JsonLogConfig {
+            json_message_fields: vec!["event".to_string()],
+            json_level_fields: vec!["lvl".to_string()],
+            ..JsonLogConfig::default()
+        };
+        let line = StyledLine::plain(r#"{"event":"user created","lvl":50,"msg":"kept as a field"}"#);
+        let compact = config.compact(&line, &Theme::default()).unwrap();
+        assert_eq!(compact.text, r#"ERROR user created msg="kept as a field""#);
+    }
+
+    #[test]
+    fn test_times_are_shown_as_time_of_day() {
+        let at = Local.with_ymd_and_hms(2024, 3, 9, 14, 5, 7).unwrap();
+        assert_eq!(format_time(&Value::from(at.timestamp_millis() + 250)), "14:05:07.250");
+        assert_eq!(format_time(&Value::from(at.timestamp() as f64 + 0.5)), "14:05:07.500");
+        assert_eq!(format_time(&Value::from(at.to_rfc3339())), "14:05:07.000");
+        assert_eq!(format_time(&Value::from("yesterday")), "yesterday");
+    }
+
+    #[test]
+    fn test_pretty_keeps_field_order() {
+        let pretty = JsonLogConfig::default().pretty(r#"{"msg":"hi","a":{"b":1}}"#).unwrap();
+        assert_eq!(pretty, "{\n  \"msg\": \"hi\",\n  \"a\": {\n    \"b\": 1\n  }\n}");
+    }
+}
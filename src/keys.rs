@@ -0,0 +1,237 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+/// A key combination such as the input-mode prefix, parsed from strings like
+/// `ctrl-]`, `alt-x` or `f12`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeySpec {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+impl KeySpec {
+    pub fn parse(spec: &str) -> Option<Self> {
+        let spec = spec.trim().to_lowercase();
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = spec.as_str();
+        loop {
+            if let Some(r) = rest.strip_prefix("ctrl-").or_else(|| rest.strip_prefix("c-")) {
+                modifiers |= KeyModifiers::CONTROL;
+                rest = r;
+            } else if let Some(r) = rest.strip_prefix("alt-").or_else(|| rest.strip_prefix("m-")) {
+                modifiers |= KeyModifiers::ALT;
+                rest = r;
+            } else {
+                break;
+            }
+        }
+        let code = match rest {
+            "esc" | "escape" => KeyCode::Esc,
+            "enter" => KeyCode::Enter,
+            "tab" => KeyCode::Tab,
+            "space" => KeyCode::Char(' '),
+            _ if rest.starts_with('f') && rest.len() > 1 => KeyCode::F(rest[1..].parse().ok()?),
+            _ => {
+                let mut chars = rest.chars();
+                let c = chars.next()?;
+                if chars.next().is_some() {
+                    return None;
+                }
+                KeyCode::Char(c)
+            }
+        };
+        Some(KeySpec { code: canonical_code(code, modifiers), modifiers })
+    }
+
+    pub fn matches(&self, key: &KeyEvent) -> bool {
+        // Shift is implied by the character itself
+        let modifiers = key.modifiers.difference(KeyModifiers::SHIFT);
+        canonical_code(key.code, modifiers) == self.code && modifiers == self.modifiers
+    }
+}
+
+/// Terminals send the same byte for ctrl-] and ctrl-5 (and likewise for
+/// `\`, `^` and `_`), and crossterm reports the digit, so compare on the symbol.
+fn canonical_code(code: KeyCode, modifiers: KeyModifiers) -> KeyCode {
+    match code {
+        KeyCode::Char(c) if modifiers.contains(KeyModifiers::CONTROL) => KeyCode::Char(match c.to_ascii_lowercase() {
+            '4' => '\\',
+            '5' => ']',
+            '6' => '^',
+            '7' => '_',
+            c => c,
+        }),
+        KeyCode::Char(c) => KeyCode::Char(c.to_ascii_lowercase()),
+        code => code,
+    }
+}
+
+impl std::fmt::Display for KeySpec {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt-")?;
+        }
+        match self.code {
+            KeyCode::Esc => write!(f, "esc"),
+            KeyCode::Enter => write!(f, "enter"),
+            KeyCode::Tab => write!(f, "tab"),
+            KeyCode::Char(' ') => write!(f, "space"),
+            KeyCode::F(n) => write!(f, "f{n}"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            _ => write!(f, "?"),
+        }
+    }
+}
+
+/// Encodes a key press as the bytes a terminal would send for it.
+/// `application_cursor` follows DECCKM, which full-screen programs turn on
+/// to receive `ESC O A` instead of `ESC [ A` for the arrow keys.
+pub fn encode_key(key: &KeyEvent, application_cursor: bool) -> Option<Vec<u8>> {
+    let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+    let alt = key.modifiers.contains(KeyModifiers::ALT);
+    // xterm modifier parameter: 1 + shift + 2*alt + 4*ctrl
+    let modifier_param = 1
+        + key.modifiers.contains(KeyModifiers::SHIFT) as u8
+        + 2 * alt as u8
+        + 4 * ctrl as u8;
+
+    let cursor = |letter: char| -> Vec<u8> {
+        if modifier_param > 1 {
+            format!("\x1b[1;{modifier_param}{letter}").into_bytes()
+        } else if application_cursor {
+            format!("\x1bO{letter}").into_bytes()
+        } else {
+            format!("\x1b[{letter}").into_bytes()
+        }
+    };
+    let tilde = |number: u8| -> Vec<u8> {
+        if modifier_param > 1 {
+            format!("\x1b[{number};{modifier_param}~").into_bytes()
+        } else {
+            format!("\x1b[{number}~").into_bytes()
+        }
+    };
+
+    let bytes = match key.code {
+        KeyCode::Char(c) if ctrl => {
+            let byte = match c.to_ascii_lowercase() {
+                c @ 'a'..='z' => c as u8 - b'a' + 1,
+                '@' | ' ' | '2' => 0,
+                '[' | '3' => 0x1b,
+                '\\' | '4' => 0x1c,
+                ']' | '5' => 0x1d,
+                '^' | '6' => 0x1e,
+                '_' | '/' | '7' => 0x1f,
+                '8' | '?' => 0x7f,
+                _ => return None,
+            };
+            if alt { vec![0x1b, byte] } else { vec![byte] }
+        }
+        KeyCode::Char(c) => {
+            let mut bytes = if alt { vec![0x1b] } else { Vec::new() };
+            let mut buf = [0u8; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            bytes
+        }
+        KeyCode::Enter => if alt { b"\x1b\r".to_vec() } else { b"\r".to_vec() },
+        KeyCode::Tab => b"\t".to_vec(),
+        KeyCode::BackTab => b"\x1b[Z".to_vec(),
+        KeyCode::Backspace => if alt { b"\x1b\x7f".to_vec() } else { b"\x7f".to_vec() },
+        KeyCode::Esc => b"\x1b".to_vec(),
+        KeyCode::Up => cursor('A'),
+        KeyCode::Down => cursor('B'),
+        KeyCode::Right => cursor('C'),
+        KeyCode::Left => cursor('D'),
+        KeyCode::Home => cursor('H'),
+        KeyCode::End => cursor('F'),
+        KeyCode::Insert => tilde(2),
+        KeyCode::Delete => tilde(3),
+        KeyCode::PageUp => tilde(5),
+        KeyCode::PageDown => tilde(6),
+        KeyCode::F(n @ 1..=4) => {
+            let letter = (b'P' + n - 1) as char;
+            if modifier_param > 1 {
+                format!("\x1b[1;{modifier_param}{letter}").into_bytes()
+            } else {
+                format!("\x1bO{letter}").into_bytes()
+            }
+        }
+        KeyCode::F(n @ 5..=12) => {
+            // The VT220 numbering skips 16 and 22
+            const CODES: [u8; 8] = [15, 17, 18, 19, 20, 21, 23, 24];
+            tilde(CODES[(n - 5) as usize])
+        }
+        _ => return None,
+    };
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn test_parse_key_spec() {
+        assert_eq!(KeySpec::parse("ctrl-]"), Some(KeySpec { code: KeyCode::Char(']'), modifiers: KeyModifiers::CONTROL }));
+        assert_eq!(KeySpec::parse("C-a"), Some(KeySpec { code: KeyCode::Char('a'), modifiers: KeyModifiers::CONTROL }));
+        assert_eq!(KeySpec::parse("alt-x"), Some(KeySpec { code: KeyCode::Char('x'), modifiers: KeyModifiers::ALT }));
+        assert_eq!(KeySpec::parse("f12"), Some(KeySpec { code: KeyCode::F(12), modifiers: KeyModifiers::NONE }));
+        assert_eq!(KeySpec::parse("ctrl-"), None);
+        assert_eq!(KeySpec::parse("ctrl-abc"), None);
+    }
+
+    #[test]
+    fn test_key_spec_display_round_trips() {
+        for spec in ["ctrl-]", "alt-x", "f12", "ctrl-alt-space", "esc"] {
+            assert_eq!(KeySpec::parse(spec).unwrap().to_string(), spec);
+        }
+    }
+
+    #[test]
+    fn test_key_spec_matches() {
+        let spec = KeySpec::parse("ctrl-b").unwrap();
+        assert!(spec.matches(&key(KeyCode::Char('b'), KeyModifiers::CONTROL)));
+        assert!(!spec.matches(&key(KeyCode::Char('b'), KeyModifiers::NONE)));
+        assert!(!spec.matches(&key(KeyCode::Char('b'), KeyModifiers::CONTROL | KeyModifiers::ALT)));
+    }
+
+    #[test]
+    fn test_ctrl_bracket_matches_crossterm_digit() {
+        let spec = KeySpec::parse("ctrl-]").unwrap();
+        assert!(spec.matches(&key(KeyCode::Char('5'), KeyModifiers::CONTROL)));
+        assert!(spec.matches(&key(KeyCode::Char(']'), KeyModifiers::CONTROL)));
+        assert!(!spec.matches(&key(KeyCode::Char('5'), KeyModifiers::NONE)));
+    }
+
+    #[test]
+    fn test_encode_printable_and_control_keys() {
+        assert_eq!(encode_key(&key(KeyCode::Char('a'), KeyModifiers::NONE), false), Some(b"a".to_vec()));
+        assert_eq!(encode_key(&key(KeyCode::Char('é'), KeyModifiers::NONE), false), Some("é".as_bytes().to_vec()));
+        assert_eq!(encode_key(&key(KeyCode::Char('c'), KeyModifiers::CONTROL), false), Some(vec![3]));
+        assert_eq!(encode_key(&key(KeyCode::Char('x'), KeyModifiers::ALT), false), Some(b"\x1bx".to_vec()));
+        assert_eq!(encode_key(&key(KeyCode::Enter, KeyModifiers::NONE), false), Some(b"\r".to_vec()));
+        assert_eq!(encode_key(&key(KeyCode::Backspace, KeyModifiers::NONE), false), Some(vec![0x7f]));
+    }
+
+    #[test]
+    fn test_encode_cursor_keys() {
+        assert_eq!(encode_key(&key(KeyCode::Up, KeyModifiers::NONE), false), Some(b"\x1b[A".to_vec()));
+        assert_eq!(encode_key(&key(KeyCode::Up, KeyModifiers::NONE), true), Some(b"\x1bOA".to_vec()));
+        assert_eq!(encode_key(&key(KeyCode::Right, KeyModifiers::CONTROL), false), Some(b"\x1b[1;5C".to_vec()));
+        assert_eq!(encode_key(&key(KeyCode::PageDown, KeyModifiers::NONE), false), Some(b"\x1b[6~".to_vec()));
+    }
+
+    #[test]
+    fn test_encode_function_keys() {
+        assert_eq!(encode_key(&key(KeyCode::F(1), KeyModifiers::NONE), false), Some(b"\x1bOP".to_vec()));
+        assert_eq!(encode_key(&key(KeyCode::F(5), KeyModifiers::NONE), false), Some(b"\x1b[15~".to_vec()));
+        assert_eq!(encode_key(&key(KeyCode::F(12), KeyModifiers::NONE), false), Some(b"\x1b[24~".to_vec()));
+        assert_eq!(encode_key(&key(KeyCode::F(13), KeyModifiers::NONE), false), None);
+    }
+}
@@ -3,10 +3,27 @@ pub mod theme;
 pub mod ui_layout;
 pub mod ui_output;
 pub mod ui_animation;
+pub mod ui_input;
+pub mod process_config;
+pub mod watch_config;
 pub mod app_config;
 pub mod config;
 pub mod cli;
 pub mod process;
+pub mod process_tree;
+pub mod restart;
+pub mod procfile;
+pub mod supervised;
+pub mod signals;
+pub mod watch;
+pub mod ansi;
+pub mod output_log;
+pub mod scrollback;
+pub mod search;
+pub mod highlight;
+pub mod json_log;
+pub mod terminal;
+pub mod keys;
 pub mod network;
 pub mod disk;
 pub mod ui;
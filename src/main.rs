@@ -7,12 +7,17 @@ use druns::gfx_demo::GfxDemoApp;
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args = Args::parse();
 
-    if args.command.is_empty() {
+    // A Procfile or a config file's [[process]] tables stand in for the command;
+    // App::new checks that the config file has some
+    let multi_process = args.procfile.is_some() || args.config.is_some();
+    if args.command.is_empty() && !multi_process {
         eprintln!("No command provided");
         std::process::exit(1);
     }
 
-    let (is_run, command) = if args.command[0] == "run" {
+    let (is_run, command) = if args.command.is_empty() {
+        (true, vec![])
+    } else if args.command[0] == "run" {
         (true, args.command[1..].to_vec())
     } else if args.command[0] == "gfx-demo" {
         (false, vec![])
@@ -21,7 +26,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     };
 
     if is_run {
-        if command.is_empty() {
+        if command.is_empty() && !multi_process {
             eprintln!("No command provided");
             std::process::exit(1);
         }
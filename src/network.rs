@@ -4,24 +4,144 @@ use netstat2::{get_sockets_info, AddressFamilyFlags, ProtocolFlags, ProtocolSock
 use pcap;
 #[cfg(feature = "experimental-pcap")]
 use etherparse;
+#[cfg(feature = "experimental-pcap")]
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use crate::process_tree::descendants;
+use procfs::net::TcpState;
+use procfs::process::{FDTarget, Process};
+use std::collections::HashSet;
+use tokio::sync::mpsc;
+use tokio::time::{sleep, Duration};
+
+/// What a process tree is doing on the network, read from /proc without
+/// capturing packets.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NetworkSample {
+    /// TCP connections and connected UDP sockets
+    pub connections: usize,
+    /// TCP sockets waiting for connections and unconnected UDP sockets
+    pub listening: usize,
+    /// Bytes received and sent over the last interval. Only known when the
+    /// tree has a network namespace of its own, such as a container's, as
+    /// the interface counters would otherwise count the whole host.
+    pub throughput: Option<(u64, u64)>,
+}
+
+/// The inodes of every socket `pids` have open.
+fn socket_inodes(pids: &[u32]) -> HashSet<u64> {
+    pids.iter()
+        .filter_map(|&pid| Process::new(pid as i32).ok()?.fd().ok())
+        .flatten()
+        .flatten()
+        .filter_map(|fd| match fd.target {
+            FDTarget::Socket(inode) => Some(inode),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Counts the sockets of `pids` in `root`'s network namespace.
+fn count_sockets(root: &Process, pids: &[u32]) -> NetworkSample {
+    let inodes = socket_inodes(pids);
+    let mut sample = NetworkSample::default();
+    let tcp = root.tcp().unwrap_or_default().into_iter().chain(root.tcp6().unwrap_or_default());
+    for entry in tcp.filter(|entry| inodes.contains(&entry.inode)) {
+        match entry.state {
+            TcpState::Listen => sample.listening += 1,
+            _ => sample.connections += 1,
+        }
+    }
+    let udp = root.udp().unwrap_or_default().into_iter().chain(root.udp6().unwrap_or_default());
+    for entry in udp.filter(|entry| inodes.contains(&entry.inode)) {
+        if entry.remote_address.port() == 0 {
+            sample.listening += 1;
+        } else {
+            sample.connections += 1;
+        }
+    }
+    sample
+}
+
+/// Bytes received and sent by every interface but loopback in `root`'s
+/// network namespace, when that isn't druns' own.
+fn namespace_bytes(root: &Process) -> Option<(u64, u64)> {
+    let namespace = |pid: &str| std::fs::read_link(format!("/proc/{pid}/ns/net")).ok();
+    let own = namespace("self")?;
+    if namespace(&root.pid.to_string())? == own {
+        return None;
+    }
+    let devices = root.dev_status().ok()?;
+    Some(
+        devices
+            .values()
+            .filter(|device| device.name != "lo")
+            .fold((0, 0), |(rx, tx), device| (rx + device.recv_bytes, tx + device.sent_bytes)),
+    )
+}
+
+/// Samples the sockets of `pid` and its descendants once a second.
+pub async fn monitor_sockets(pid: u32, tx: mpsc::UnboundedSender<NetworkSample>) {
+    let mut previous_bytes = None;
+    loop {
+        let mut sample = NetworkSample::default();
+        if let Ok(root) = Process::new(pid as i32) {
+            sample = count_sockets(&root, &descendants(pid));
+            let bytes = namespace_bytes(&root);
+            if let (Some((rx, tx)), Some((previous_rx, previous_tx))) = (bytes, previous_bytes) {
+                sample.throughput = Some((rx.saturating_sub(previous_rx), tx.saturating_sub(previous_tx)));
+            }
+            previous_bytes = bytes;
+        }
+        if tx.send(sample).is_err() {
+            // Nobody is listening any more, e.g. after a restart
+            break;
+        }
+        sleep(Duration::from_secs(1)).await;
+    }
+}
+
+/// A socket of the process tree, as the packet capture looks for it.
+#[cfg(feature = "experimental-pcap")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapturedSocket {
+    pub protocol: &'static str,
+    pub local: SocketAddr,
+    /// Where a TCP socket is connected to; UDP sockets can talk to anyone
+    pub remote: Option<SocketAddr>,
+}
+
+#[cfg(feature = "experimental-pcap")]
+impl CapturedSocket {
+    fn is_local(&self, protocol: &str, address: SocketAddr) -> bool {
+        self.protocol == protocol
+            && self.local.port() == address.port()
+            && (self.local.ip().is_unspecified() || self.local.ip() == address.ip())
+    }
 
+    fn is_remote(&self, address: SocketAddr) -> bool {
+        self.remote.is_none_or(|remote| remote == address)
+    }
+}
+
+/// The TCP and UDP sockets any of `pids` has open, over IPv4 and IPv6.
 #[cfg(feature = "experimental-pcap")]
-pub fn get_process_connections(pid: u32) -> Vec<(String, u16, String, u16, String)> {
+pub fn get_process_connections(pids: &[u32]) -> Vec<CapturedSocket> {
     let mut connections = Vec::new();
     if let Ok(sockets) = get_sockets_info(AddressFamilyFlags::IPV4 | AddressFamilyFlags::IPV6, ProtocolFlags::TCP | ProtocolFlags::UDP) {
         for socket in sockets {
-            if socket.associated_pids.contains(&pid) {
+            if socket.associated_pids.iter().any(|pid| pids.contains(pid)) {
                 match socket.protocol_socket_info {
-                    ProtocolSocketInfo::Tcp(tcp_info) => {
-                        let local_ip = tcp_info.local_addr.to_string();
-                        let local_port = tcp_info.local_port;
-                        let remote_ip = tcp_info.remote_addr.to_string();
-                        let remote_port = tcp_info.remote_port;
-                        connections.push((local_ip, local_port, remote_ip, remote_port, "tcp".to_string()));
-                    }
-                    ProtocolSocketInfo::Udp(_udp_info) => {
-                        // UDP connections don't have remote addr in this crate, skip for now
-                    }
+                    ProtocolSocketInfo::Tcp(tcp_info) => connections.push(CapturedSocket {
+                        protocol: "tcp",
+                        local: SocketAddr::new(tcp_info.local_addr, tcp_info.local_port),
+                        remote: Some(SocketAddr::new(tcp_info.remote_addr, tcp_info.remote_port)),
+                    }),
+                    ProtocolSocketInfo::Udp(udp_info) => connections.push(CapturedSocket {
+                        protocol: "udp",
+                        local: SocketAddr::new(udp_info.local_addr, udp_info.local_port),
+                        remote: None,
+                    }),
                 }
             }
         }
@@ -30,13 +150,27 @@ pub fn get_process_connections(pid: u32) -> Vec<(String, u16, String, u16, Strin
 }
 
 #[cfg(feature = "experimental-pcap")]
-pub fn build_bpf_filter(connections: &[(String, u16, String, u16, String)]) -> String {
+pub fn build_bpf_filter(connections: &[CapturedSocket]) -> String {
+    let endpoint = |direction: &str, address: SocketAddr| {
+        if address.ip().is_unspecified() {
+            format!("{direction} port {}", address.port())
+        } else {
+            format!("{direction} host {} and {direction} port {}", address.ip(), address.port())
+        }
+    };
     let mut filters = Vec::new();
-    for (local_ip, local_port, remote_ip, remote_port, proto) in connections {
-        let filter = format!(
-            "({} and ((src host {} and src port {} and dst host {} and dst port {}) or (dst host {} and dst port {} and src host {} and src port {})))",
-            proto, local_ip, local_port, remote_ip, remote_port, local_ip, local_port, remote_ip, remote_port
-        );
+    for socket in connections {
+        let filter = match socket.remote {
+            Some(remote) => format!(
+                "({} and (({} and {}) or ({} and {})))",
+                socket.protocol,
+                endpoint("src", socket.local),
+                endpoint("dst", remote),
+                endpoint("dst", socket.local),
+                endpoint("src", remote)
+            ),
+            None => format!("({} and (({}) or ({})))", socket.protocol, endpoint("src", socket.local), endpoint("dst", socket.local)),
+        };
         filters.push(filter);
     }
     if filters.is_empty() {
@@ -46,61 +180,156 @@ pub fn build_bpf_filter(connections: &[(String, u16, String, u16, String)]) -> S
     }
 }
 
+/// Which way a packet went, as seen from the process tree.
 #[cfg(feature = "experimental-pcap")]
-pub async fn monitor_network(pid: u32, tx: tokio::sync::mpsc::UnboundedSender<(u64, u64)>) {
-    loop {
-        // Get current connections for the process
-        let connections = get_process_connections(pid);
-        let filter = build_bpf_filter(&connections);
-
-        // Capture packets for 1 second
-        let mut total_sent = 0u64;
-        let mut total_recv = 0u64;
-
-        if let Ok(devices) = pcap::Device::list() {
-            if let Some(device) = devices.into_iter().next() {
-                if let Ok(cap) = pcap::Capture::from_device(device) {
-                    if let Ok(mut cap) = cap.timeout(1000).open() {
-                        if cap.filter(&filter, true).is_ok() {
-                            let start_time = tokio::time::Instant::now();
-                            while start_time.elapsed() < tokio::time::Duration::from_secs(1) {
-                                match cap.next_packet() {
-                                    Ok(packet) => {
-                                        if let Ok(headers) = etherparse::PacketHeaders::from_ethernet_slice(&packet.data) {
-                                            let payload_len = headers.payload.len() as u64;
-                                            // Determine if sent or received
-                                            if let Some(etherparse::IpHeader::Version4(ipv4, _)) = headers.ip {
-                                                let src_ip = std::net::Ipv4Addr::from(ipv4.source);
-                                                let is_sent = connections.iter().any(|(local_ip, _, _, _, _)| {
-                                                    if let Ok(lip) = local_ip.parse::<std::net::Ipv4Addr>() {
-                                                        lip == src_ip
-                                                    } else {
-                                                        false
-                                                    }
-                                                });
-                                                if is_sent {
-                                                    total_sent += payload_len;
-                                                } else {
-                                                    total_recv += payload_len;
-                                                }
-                                            } else if let Some(etherparse::IpHeader::Version6(_ipv6, _)) = headers.ip {
-                                                // Handle IPv6, for now skip
-                                            }
-                                        }
-                                    }
-                                    Err(_) => break,
-                                }
-                            }
-                        }
-                    }
-                }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// Tells whether a packet from `source` to `destination` was sent or
+/// received by one of `connections`, or neither.
+#[cfg(feature = "experimental-pcap")]
+pub fn direction(connections: &[CapturedSocket], protocol: &str, source: SocketAddr, destination: SocketAddr) -> Option<Direction> {
+    connections.iter().find_map(|socket| {
+        if socket.is_local(protocol, source) && socket.is_remote(destination) {
+            Some(Direction::Sent)
+        } else if socket.is_local(protocol, destination) && socket.is_remote(source) {
+            Some(Direction::Received)
+        } else {
+            None
+        }
+    })
+}
+
+/// The protocol, addresses and payload size of a captured TCP or UDP packet.
+/// The "any" device hands out Linux cooked headers instead of Ethernet ones.
+#[cfg(feature = "experimental-pcap")]
+fn parse_packet(linktype: pcap::Linktype, data: &[u8]) -> Option<(&'static str, SocketAddr, SocketAddr, u64)> {
+    let headers = match linktype.0 {
+        // DLT_EN10MB
+        1 => etherparse::PacketHeaders::from_ethernet_slice(data),
+        // DLT_LINUX_SLL and DLT_LINUX_SLL2
+        113 => etherparse::PacketHeaders::from_ip_slice(data.get(16..)?),
+        276 => etherparse::PacketHeaders::from_ip_slice(data.get(20..)?),
+        // Raw IP, as on tun devices
+        12 | 14 | 101 => etherparse::PacketHeaders::from_ip_slice(data),
+        _ => return None,
+    }
+    .ok()?;
+    let (source, destination): (IpAddr, IpAddr) = match headers.ip? {
+        etherparse::IpHeader::Version4(ip, _) => (Ipv4Addr::from(ip.source).into(), Ipv4Addr::from(ip.destination).into()),
+        etherparse::IpHeader::Version6(ip, _) => (Ipv6Addr::from(ip.source).into(), Ipv6Addr::from(ip.destination).into()),
+    };
+    let (protocol, source_port, destination_port) = match headers.transport? {
+        etherparse::TransportHeader::Tcp(tcp) => ("tcp", tcp.source_port, tcp.destination_port),
+        etherparse::TransportHeader::Udp(udp) => ("udp", udp.source_port, udp.destination_port),
+        _ => return None,
+    };
+    Some((
+        protocol,
+        SocketAddr::new(source, source_port),
+        SocketAddr::new(destination, destination_port),
+        headers.payload.len() as u64,
+    ))
+}
+
+/// Captures the tree's traffic on `device` for a second, as bytes received
+/// and sent.
+#[cfg(feature = "experimental-pcap")]
+fn capture_once(device: &str, connections: &[CapturedSocket]) -> Result<(u64, u64), pcap::Error> {
+    let mut cap = pcap::Capture::from_device(device)?.timeout(1000).open()?;
+    cap.filter(&build_bpf_filter(connections), true)?;
+    let linktype = cap.get_datalink();
+
+    let mut total_sent = 0u64;
+    let mut total_recv = 0u64;
+    let start_time = std::time::Instant::now();
+    while start_time.elapsed() < Duration::from_secs(1) {
+        let packet = match cap.next_packet() {
+            Ok(packet) => packet,
+            Err(_) => break,
+        };
+        if let Some((protocol, source, destination, size)) = parse_packet(linktype, packet.data) {
+            match direction(connections, protocol, source, destination) {
+                Some(Direction::Sent) => total_sent += size,
+                Some(Direction::Received) => total_recv += size,
+                None => {}
             }
         }
+    }
+    Ok((total_recv, total_sent))
+}
 
-        // Send the stats
-        let _ = tx.send((total_recv, total_sent));
+/// Measures the traffic of `pid` and its descendants on `device`, a name
+/// such as `eth0` or `any` for every interface. Nothing is sent while the
+/// device can't be captured on, e.g. without root.
+#[cfg(feature = "experimental-pcap")]
+pub async fn monitor_network(pid: u32, device: String, tx: mpsc::UnboundedSender<(u64, u64)>) {
+    loop {
+        // Get current connections for the process and everything it started
+        let connections = get_process_connections(&descendants(pid));
+        if let Ok(stats) = capture_once(&device, &connections) {
+            if tx.send(stats).is_err() {
+                break;
+            }
+        } else if tx.is_closed() {
+            break;
+        }
 
         // Wait before next capture
-        tokio::time::sleep(tokio::time::Duration::from_secs(1)).await;
+        sleep(Duration::from_secs(1)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{TcpListener, TcpStream, UdpSocket};
+
+    #[test]
+    fn test_sockets_of_the_tree_are_counted() {
+        let pid = std::process::id();
+        let root = Process::myself().unwrap();
+        let before = count_sockets(&root, &[pid]);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        let udp = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let sample = count_sockets(&root, &[pid]);
+        drop((client, server, udp));
+
+        // Other tests may open sockets of their own meanwhile
+        assert!(sample.connections >= before.connections + 2, "{before:?} {sample:?}");
+        assert!(sample.listening >= before.listening + 2, "{before:?} {sample:?}");
+        assert_eq!(count_sockets(&root, &[]), NetworkSample::default());
+        // The tests share druns' network namespace
+        assert_eq!(namespace_bytes(&root), None);
+    }
+
+    #[cfg(feature = "experimental-pcap")]
+    #[test]
+    fn test_direction_over_ipv4_and_ipv6() {
+        let address = |text: &str| text.parse::<SocketAddr>().unwrap();
+        let connections = [
+            CapturedSocket { protocol: "tcp", local: address("10.0.0.2:40000"), remote: Some(address("93.184.216.34:443")) },
+            CapturedSocket { protocol: "udp", local: address("[::]:5353"), remote: None },
+        ];
+        let tcp = |source, destination| direction(&connections, "tcp", address(source), address(destination));
+        assert_eq!(tcp("10.0.0.2:40000", "93.184.216.34:443"), Some(Direction::Sent));
+        assert_eq!(tcp("93.184.216.34:443", "10.0.0.2:40000"), Some(Direction::Received));
+        assert_eq!(tcp("10.0.0.2:40001", "93.184.216.34:443"), None);
+        let udp = |source, destination| direction(&connections, "udp", address(source), address(destination));
+        assert_eq!(udp("[fe80::1]:5353", "[ff02::fb]:5353"), Some(Direction::Sent));
+        assert_eq!(udp("[2001:db8::7]:53", "[fe80::1]:5353"), Some(Direction::Received));
+        assert_eq!(tcp("[fe80::1]:5353", "[ff02::fb]:5353"), None);
+
+        assert_eq!(
+            build_bpf_filter(&connections[1..]),
+            "(udp and ((src port 5353) or (dst port 5353)))"
+        );
+        assert_eq!(build_bpf_filter(&[]), "false");
     }
 }
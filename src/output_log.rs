@@ -0,0 +1,149 @@
+use crate::ansi::OutputStream;
+use chrono::{DateTime, Local};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// The `--log` file, shared between the output reader tasks.
+#[derive(Debug, Clone)]
+pub struct OutputLog {
+    file: Arc<Mutex<File>>,
+    /// Keep escape sequences instead of writing the stripped text
+    raw: bool,
+    /// Process name written before each line when several processes share the log
+    prefix: Option<String>,
+    /// Start each line with the time it arrived
+    timestamps: bool,
+}
+
+impl OutputLog {
+    pub fn open<P: AsRef<Path>>(path: P, raw: bool) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(OutputLog { file: Arc::new(Mutex::new(file)), raw, prefix: None, timestamps: false })
+    }
+
+    /// The same log, with each line starting with its arrival time.
+    pub fn with_timestamps(self) -> Self {
+        OutputLog { timestamps: true, ..self }
+    }
+
+    /// The same log, with every line written through it labelled `name | `.
+    pub fn with_prefix(&self, name: &str) -> Self {
+        OutputLog { prefix: Some(format!("{name} | ")), ..self.clone() }
+    }
+
+    /// Writes one line, picking the raw or stripped form depending on how the log was opened.
+    pub fn write_line(&self, raw: &str, stripped: &str) {
+        self.write_line_at(Local::now(), raw, stripped);
+    }
+
+    /// Writes one line that arrived at `time`.
+    pub fn write_line_at(&self, time: DateTime<Local>, raw: &str, stripped: &str) {
+        let line = if self.raw { raw } else { stripped };
+        let mut file = self.file.lock().unwrap();
+        let prefix = self.prefix.as_deref().unwrap_or("");
+        let timestamp = if self.timestamps { time.format("%Y-%m-%d %H:%M:%S%.3f ").to_string() } else { String::new() };
+        let _ = file.write_all(format!("{timestamp}{prefix}{line}\n").as_bytes());
+        let _ = file.flush();
+    }
+}
+
+/// Where a process' output is logged: everything to the `--log` file, with
+/// stderr lines marked, unless `--log-stderr` gives stderr a file of its own.
+#[derive(Debug, Clone, Default)]
+pub struct OutputLogs {
+    pub log: Option<OutputLog>,
+    pub stderr_log: Option<OutputLog>,
+}
+
+impl OutputLogs {
+    pub fn with_prefix(&self, name: &str) -> Self {
+        OutputLogs {
+            log: self.log.as_ref().map(|log| log.with_prefix(name)),
+            stderr_log: self.stderr_log.as_ref().map(|log| log.with_prefix(name)),
+        }
+    }
+
+    pub fn write_line(&self, time: DateTime<Local>, stream: OutputStream, raw: &str, stripped: &str) {
+        match (stream, &self.stderr_log, &self.log) {
+            (OutputStream::Stderr, Some(stderr_log), _) => stderr_log.write_line_at(time, raw, stripped),
+            (OutputStream::Stderr, None, Some(log)) => {
+                log.write_line_at(time, &format!("[stderr] {raw}"), &format!("[stderr] {stripped}"));
+            }
+            (_, _, Some(log)) => log.write_line_at(time, raw, stripped),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_writes_stripped_text_by_default() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("out.log");
+        let log = OutputLog::open(&path, false).unwrap();
+        log.write_line("\x1b[31mred\x1b[0m", "red");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "red\n");
+    }
+
+    #[test]
+    fn test_raw_log_keeps_escape_sequences() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("out.log");
+        let log = OutputLog::open(&path, true).unwrap();
+        log.write_line("\x1b[31mred\x1b[0m", "red");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "\x1b[31mred\x1b[0m\n");
+    }
+
+    #[test]
+    fn test_stderr_is_marked_in_a_shared_log() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("out.log");
+        let logs = OutputLogs { log: Some(OutputLog::open(&path, false).unwrap()), stderr_log: None };
+        logs.write_line(Local::now(), OutputStream::Stdout, "ok", "ok");
+        logs.write_line(Local::now(), OutputStream::Stderr, "oops", "oops");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "ok\n[stderr] oops\n");
+    }
+
+    #[test]
+    fn test_stderr_can_have_its_own_log() {
+        let dir = tempdir().unwrap();
+        let out_path = dir.path().join("out.log");
+        let err_path = dir.path().join("err.log");
+        let logs = OutputLogs {
+            log: Some(OutputLog::open(&out_path, false).unwrap()),
+            stderr_log: Some(OutputLog::open(&err_path, false).unwrap()),
+        };
+        logs.write_line(Local::now(), OutputStream::Stdout, "ok", "ok");
+        logs.write_line(Local::now(), OutputStream::Stderr, "oops", "oops");
+        logs.write_line(Local::now(), OutputStream::Druns, "── restart ──", "── restart ──");
+        assert_eq!(std::fs::read_to_string(&out_path).unwrap(), "ok\n── restart ──\n");
+        assert_eq!(std::fs::read_to_string(&err_path).unwrap(), "oops\n");
+    }
+
+    #[test]
+    fn test_timestamps_come_first() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("out.log");
+        let log = OutputLog::open(&path, false).unwrap().with_timestamps();
+        let time = Local.with_ymd_and_hms(2024, 5, 6, 7, 8, 9).unwrap();
+        log.with_prefix("web").write_line_at(time, "listening", "listening");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "2024-05-06 07:08:09.000 web | listening\n");
+    }
+
+    #[test]
+    fn test_prefixed_logs_share_the_file() {
+        let dir = tempdir().unwrap();
+        let path = dir.path().join("out.log");
+        let log = OutputLog::open(&path, false).unwrap();
+        log.with_prefix("web").write_line("listening", "listening");
+        log.with_prefix("worker").write_line("ready", "ready");
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "web | listening\nworker | ready\n");
+    }
+}
@@ -1,96 +1,505 @@
-use std::io::Write;
-use std::sync::Arc;
-use tokio::io::AsyncBufReadExt;
+use crate::ansi::{AnsiParser, OutputStream, StyledLine};
+use crate::output_log::OutputLogs;
+use crate::terminal::TerminalScreen;
+use portable_pty::{native_pty_system, CommandBuilder, MasterPty, PtySize};
+use std::io::{Read, Write};
+use std::os::unix::process::ExitStatusExt;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::process::Command;
 use tokio::sync::mpsc;
-use tokio_stream::StreamExt;
+use tokio::time::{sleep, timeout, Duration, Instant};
 
+/// How the child's stdout and stderr are connected to druns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputMode {
+    /// Run the child under a pseudo-terminal so it behaves as if attached to a TTY.
+    Pty,
+    /// Capture stdout and stderr through plain pipes.
+    Pipes,
+}
+
+#[derive(Debug, Clone)]
+pub struct SpawnOptions {
+    pub mode: OutputMode,
+    /// Initial terminal width handed to the child
+    pub cols: u16,
+    /// Initial terminal height handed to the child
+    pub rows: u16,
+    /// Run the arguments as one script with this shell instead of executing them directly
+    pub shell: Option<String>,
+}
+
+impl Default for SpawnOptions {
+    fn default() -> Self {
+        SpawnOptions {
+            mode: OutputMode::Pipes,
+            cols: 80,
+            rows: 24,
+            shell: None,
+        }
+    }
+}
+
+/// The argv actually executed: the command as given, or its arguments joined
+/// into a single `-c` script when a shell is requested.
+pub fn command_argv(command: &[String], shell: Option<&str>) -> Vec<String> {
+    match shell {
+        Some(shell) => vec![shell.to_string(), "-c".to_string(), command.join(" ")],
+        None => command.to_vec(),
+    }
+}
+
+enum ChildHandle {
+    Pipes(tokio::process::Child),
+    Pty {
+        child: Box<dyn portable_pty::Child + Send + Sync>,
+        master: Box<dyn MasterPty + Send>,
+        screen: TerminalScreen,
+    },
+}
+
+/// The child leads its own process group (a new session under a PTY), so its
+/// pid doubles as the group id for everything it starts.
 pub struct ProcessManager {
-    pub child: tokio::process::Child,
+    child: ChildHandle,
+    /// Input on its way to the child, written off the event loop so that a
+    /// child that stops reading can't block druns. Started on first use in
+    /// pipe mode, where writing needs the runtime.
+    input: Option<mpsc::UnboundedSender<Vec<u8>>>,
     pub pid: u32,
     pub ppid: u32,
+    pub mode: OutputMode,
 }
 
 impl ProcessManager {
-    pub fn new(command: &[String]) -> Result<Self, Box<dyn std::error::Error>> {
-        let mut child_cmd = Command::new("sh");
-        child_cmd.args(["-c", &command.join(" ")]);
-        child_cmd.stdout(std::process::Stdio::piped());
-        child_cmd.stderr(std::process::Stdio::piped());
-        let child = child_cmd.spawn()?;
-        let pid = child.id().unwrap_or(0);
+    pub fn new(command: &[String], options: &SpawnOptions) -> Result<Self, Box<dyn std::error::Error>> {
+        let argv = command_argv(command, options.shell.as_deref());
+        let (program, args) = argv.split_first().ok_or("No command provided")?;
+        let mut input = None;
+        let child = match options.mode {
+            OutputMode::Pipes => {
+                let mut child_cmd = Command::new(program);
+                child_cmd.args(args);
+                child_cmd.env("COLUMNS", options.cols.to_string());
+                child_cmd.env("LINES", options.rows.to_string());
+                child_cmd.stdin(std::process::Stdio::piped());
+                child_cmd.stdout(std::process::Stdio::piped());
+                child_cmd.stderr(std::process::Stdio::piped());
+                // Own process group, so signals reach everything the command starts
+                child_cmd.process_group(0);
+                ChildHandle::Pipes(child_cmd.spawn()?)
+            }
+            OutputMode::Pty => {
+                let pair = native_pty_system().openpty(pty_size(options.cols, options.rows))?;
+                let mut builder = CommandBuilder::new(program);
+                builder.args(args);
+                builder.cwd(std::env::current_dir()?);
+                let child = pair.slave.spawn_command(builder)?;
+                // Drop our copy of the slave so the reader sees EOF once the child exits
+                drop(pair.slave);
+                let writer = pair.master.take_writer()?;
+                let (input_tx, input_rx) = mpsc::unbounded_channel();
+                std::thread::spawn(move || write_pty(writer, input_rx));
+                input = Some(input_tx);
+                let screen = TerminalScreen::new(options.rows, options.cols);
+                ChildHandle::Pty { child, master: pair.master, screen }
+            }
+        };
+        let pid = match &child {
+            ChildHandle::Pipes(child) => child.id(),
+            ChildHandle::Pty { child, .. } => child.process_id(),
+        }
+        .unwrap_or(0);
         let ppid = std::process::id();
 
-        Ok(ProcessManager { child, pid, ppid })
+        Ok(ProcessManager { child, input, pid, ppid, mode: options.mode })
     }
 
-    pub fn start_output_reading(
-        &mut self,
-        log_file: Option<Arc<std::sync::Mutex<std::fs::File>>>,
-    ) -> mpsc::UnboundedReceiver<String> {
+    /// Starts reading the child's output. Each line is tagged with its stream;
+    /// under a PTY both streams arrive through the terminal as stdout.
+    pub fn start_output_reading(&mut self, logs: OutputLogs) -> mpsc::UnboundedReceiver<StyledLine> {
         let (tx, rx) = mpsc::unbounded_channel();
-        if let Some(stdout) = self.child.stdout.take() {
-            let tx = tx.clone();
-            let log_file = log_file.clone();
-            tokio::spawn(async move {
-                let mut reader = tokio_stream::wrappers::LinesStream::new(
-                    tokio::io::BufReader::new(stdout).lines()
-                );
-                while let Some(line) = reader.next().await {
-                    if let Ok(line) = line {
-                        if let Some(file) = &log_file {
-                            let mut file = file.lock().unwrap();
-                            let _ = std::io::Write::write_all(&mut *file, format!("{line}\n").as_bytes());
-                            let _ = file.flush();
-                        }
-                        let _ = tx.send(line);
-                    }
+        match &mut self.child {
+            ChildHandle::Pipes(child) => {
+                if let Some(stdout) = child.stdout.take() {
+                    let forwarder = LineForwarder::new(OutputStream::Stdout, tx.clone(), logs.clone());
+                    tokio::spawn(read_pipe(stdout, forwarder));
+                }
+                if let Some(stderr) = child.stderr.take() {
+                    let forwarder = LineForwarder::new(OutputStream::Stderr, tx.clone(), logs.clone());
+                    tokio::spawn(read_pipe(stderr, forwarder));
                 }
-            });
-        }
-
-        if let Some(stderr) = self.child.stderr.take() {
-            let tx = tx.clone();
-            let log_file = log_file.clone();
-            tokio::spawn(async move {
-                let mut reader = tokio_stream::wrappers::LinesStream::new(
-                    tokio::io::BufReader::new(stderr).lines()
-                );
-                while let Some(line) = reader.next().await {
-                    if let Ok(line) = line {
-                        if let Some(file) = &log_file {
-                            let mut file = file.lock().unwrap();
-                            let _ = std::io::Write::write_all(&mut *file, format!("{line}\n").as_bytes());
-                            let _ = file.flush();
-                        }
-                        let _ = tx.send(line);
-                    }
+            }
+            ChildHandle::Pty { master, screen, .. } => {
+                if let Ok(reader) = master.try_clone_reader() {
+                    let forwarder = LineForwarder::new(OutputStream::Stdout, tx.clone(), logs);
+                    let screen = screen.clone();
+                    std::thread::spawn(move || read_pty(reader, screen, forwarder));
                 }
-            });
+            }
         }
 
         rx
     }
 
+    /// Resizes the child's terminal. Pipe mode has no terminal, so this is a no-op there.
+    pub fn resize(&self, cols: u16, rows: u16) -> Result<(), Box<dyn std::error::Error>> {
+        if let ChildHandle::Pty { master, screen, .. } = &self.child {
+            master.resize(pty_size(cols, rows))?;
+            screen.resize(rows, cols);
+        }
+        Ok(())
+    }
+
+    /// Queues keyboard input for the child. It fails once the child has
+    /// stopped taking input.
+    pub fn write_input(&mut self, bytes: &[u8]) -> Result<(), std::io::Error> {
+        let bytes = match &mut self.child {
+            ChildHandle::Pipes(child) => {
+                if self.input.is_none() {
+                    let Some(stdin) = child.stdin.take() else { return Ok(()) };
+                    let (input_tx, input_rx) = mpsc::unbounded_channel();
+                    tokio::spawn(write_pipe(stdin, input_rx));
+                    self.input = Some(input_tx);
+                }
+                // Without a terminal there is no line discipline to turn Enter into a newline
+                bytes.iter().map(|&b| if b == b'\r' { b'\n' } else { b }).collect()
+            }
+            ChildHandle::Pty { .. } => bytes.to_vec(),
+        };
+        let closed = || std::io::Error::new(std::io::ErrorKind::BrokenPipe, "the command no longer takes input");
+        self.input.as_ref().ok_or_else(closed)?.send(bytes).map_err(|_| closed())
+    }
+
+    /// The emulated terminal the child draws on; only PTY mode has one.
+    pub fn screen(&self) -> Option<&TerminalScreen> {
+        match &self.child {
+            ChildHandle::Pty { screen, .. } => Some(screen),
+            ChildHandle::Pipes(_) => None,
+        }
+    }
+
+    /// Sends a signal to the child's whole process group.
+    pub fn signal(&self, signal: i32) -> Result<(), std::io::Error> {
+        // killpg(0) would signal druns' own group
+        if self.pid == 0 {
+            return Ok(());
+        }
+        if unsafe { libc::killpg(self.pid as libc::pid_t, signal) } == 0 {
+            Ok(())
+        } else {
+            Err(std::io::Error::last_os_error())
+        }
+    }
+
+    /// Asks the process group to exit with `signal`, waking it first in case it was stopped.
+    pub fn terminate(&self, signal: i32) -> Result<(), std::io::Error> {
+        self.signal(signal)?;
+        self.signal(libc::SIGCONT)
+    }
+
+    /// SIGKILLs the whole process group.
     pub async fn kill(&mut self) -> Result<(), std::io::Error> {
-        self.child.kill().await
+        self.signal(libc::SIGKILL)
+    }
+
+    /// Sends SIGTERM, then SIGKILL if the child is still running after `grace`.
+    pub async fn stop(&mut self, grace: Duration) -> Result<std::process::ExitStatus, std::io::Error> {
+        self.terminate(libc::SIGTERM)?;
+        if let Ok(status) = timeout(grace, self.wait()).await {
+            return status;
+        }
+        self.kill().await?;
+        self.wait().await
+    }
+
+    /// Stops whatever the child left running in its process group after it
+    /// exited, giving it `grace` to handle SIGTERM before SIGKILL.
+    pub async fn stop_leftovers(&self, grace: Duration) {
+        if self.terminate(libc::SIGTERM).is_err() {
+            // Nothing left in the group
+            return;
+        }
+        let deadline = Instant::now() + grace;
+        while Instant::now() < deadline {
+            if self.signal(0).is_err() {
+                return;
+            }
+            sleep(Duration::from_millis(20)).await;
+        }
+        let _ = self.signal(libc::SIGKILL);
     }
 
     pub async fn wait(&mut self) -> Result<std::process::ExitStatus, std::io::Error> {
-        self.child.wait().await
+        match &mut self.child {
+            ChildHandle::Pipes(child) => child.wait().await,
+            ChildHandle::Pty { child, .. } => loop {
+                if let Some(status) = child.try_wait()? {
+                    return Ok(pty_exit_status(&status));
+                }
+                sleep(Duration::from_millis(20)).await;
+            },
+        }
     }
 
     pub fn try_wait(&mut self) -> Result<Option<std::process::ExitStatus>, std::io::Error> {
-        self.child.try_wait()
+        match &mut self.child {
+            ChildHandle::Pipes(child) => child.try_wait(),
+            ChildHandle::Pty { child, .. } => Ok(child.try_wait()?.map(|status| pty_exit_status(&status))),
+        }
+    }
+}
+
+fn pty_size(cols: u16, rows: u16) -> PtySize {
+    PtySize {
+        rows: rows.max(1),
+        cols: cols.max(1),
+        pixel_width: 0,
+        pixel_height: 0,
+    }
+}
+
+fn pty_exit_status(status: &portable_pty::ExitStatus) -> std::process::ExitStatus {
+    // portable-pty only keeps the exit code, so rebuild a wait(2)-style status from it
+    std::process::ExitStatus::from_raw(((status.exit_code() & 0xff) << 8) as i32)
+}
+
+/// Splits a byte stream into lines, holding back any trailing partial line
+/// until the rest of it arrives.
+///
+/// Redraws of the current line, with `\r` or by moving to the first column,
+/// are followed. Redraws of earlier lines after moving the cursor up, as
+/// docker pull does for each layer, are not: every update is a new line.
+#[derive(Default)]
+pub struct LineSplitter {
+    pending: Vec<u8>,
+}
+
+impl LineSplitter {
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<String> {
+        // The end of the last chunk may hold the start of an escape sequence
+        let fresh = self.pending.len().saturating_sub(3);
+        self.pending.extend_from_slice(chunk);
+        first_column_to_cr(&mut self.pending, fresh);
+
+        let mut lines = Vec::new();
+        let mut line_start = 0;
+        while let Some(pos) = self.pending[line_start..].iter().position(|&b| b == b'\n') {
+            lines.push(decode_line(&self.pending[line_start..line_start + pos]));
+            line_start += pos + 1;
+        }
+        self.pending.drain(..line_start);
+        let fresh = fresh.saturating_sub(line_start);
+        if let Some(pos) = self.pending[fresh..].iter().rposition(|&b| b == b'\r') {
+            self.drop_redraws(fresh + pos);
+        }
+        lines
+    }
+
+    /// Forgets the redraws before the last one finished with the `\r` at
+    /// `end`, so a progress bar that never ends its line doesn't pile up.
+    /// Only the last non-empty segment before a `\r` is ever shown, and the
+    /// `\r` itself stays for a PTY's `\r\n`.
+    fn drop_redraws(&mut self, end: usize) {
+        let mut segment_end = end;
+        while segment_end > 0 && self.pending[segment_end - 1] == b'\r' {
+            segment_end -= 1;
+        }
+        let start = if segment_end == 0 {
+            end
+        } else {
+            self.pending[..segment_end].iter().rposition(|&b| b == b'\r').map_or(0, |pos| pos + 1)
+        };
+        self.pending.drain(..start);
+    }
+
+    /// What the line being written shows so far, once it has been redrawn
+    /// with `\r` the way progress bars do.
+    pub fn progress(&self) -> Option<String> {
+        let end = self.pending.iter().rposition(|&b| b == b'\r')?;
+        Some(String::from_utf8_lossy(last_segment(&self.pending[..end])).into_owned())
+    }
+
+    /// Returns whatever is left once the stream has ended.
+    pub fn finish(&mut self) -> Option<String> {
+        if self.pending.is_empty() {
+            None
+        } else {
+            let line = std::mem::take(&mut self.pending);
+            Some(decode_line(&line))
+        }
     }
 }
 
+/// Moving the cursor to the first column (`ESC [ G`, `ESC [ 1 G`) starts the
+/// line over just like `\r`, so from `from` on each one is replaced with one.
+fn first_column_to_cr(bytes: &mut Vec<u8>, from: usize) {
+    let mut i = from;
+    while let Some(pos) = bytes[i..].windows(2).position(|window| window == b"\x1b[") {
+        let start = i + pos;
+        let params = &bytes[start + 2..];
+        let params_len = params.iter().take_while(|b| b.is_ascii_digit()).count();
+        if params.get(params_len) == Some(&b'G') && matches!(&params[..params_len], b"" | b"0" | b"1") {
+            bytes.splice(start..start + 2 + params_len + 1, [b'\r']);
+            i = start + 1;
+        } else {
+            i = start + 2;
+        }
+    }
+}
+
+fn decode_line(bytes: &[u8]) -> String {
+    // PTYs translate "\n" into "\r\n"
+    let bytes = bytes.strip_suffix(b"\r").unwrap_or(bytes);
+    String::from_utf8_lossy(last_segment(bytes)).into_owned()
+}
+
+/// A `\r` takes the cursor back to the start of the line, so what stays on
+/// screen is the last thing written after one.
+fn last_segment(bytes: &[u8]) -> &[u8] {
+    bytes.rsplit(|&b| b == b'\r').find(|segment| !segment.is_empty()).unwrap_or(b"")
+}
+
+/// Turns the raw lines of one output stream into styled lines for the UI,
+/// writing each to the log on the way.
+struct LineForwarder {
+    splitter: LineSplitter,
+    parser: AnsiParser,
+    tx: mpsc::UnboundedSender<StyledLine>,
+    stream: OutputStream,
+    logs: OutputLogs,
+    /// The progress line last sent, so an unchanged one isn't sent again
+    progress: Option<String>,
+}
+
+impl LineForwarder {
+    fn new(stream: OutputStream, tx: mpsc::UnboundedSender<StyledLine>, logs: OutputLogs) -> Self {
+        LineForwarder { splitter: LineSplitter::default(), parser: AnsiParser::new(), tx, stream, logs, progress: None }
+    }
+
+    fn push(&mut self, chunk: &[u8]) {
+        for line in self.splitter.push(chunk) {
+            self.forward(&line);
+        }
+        if let Some(progress) = self.splitter.progress() {
+            if self.progress.as_ref() != Some(&progress) {
+                self.forward_progress(&progress);
+                self.progress = Some(progress);
+            }
+        }
+    }
+
+    fn finish(&mut self) {
+        if let Some(line) = self.splitter.finish() {
+            self.forward(&line);
+        }
+    }
+
+    /// Sends the state of an unfinished progress line. Only the finished line
+    /// is logged, and the parser's colors are left for it.
+    fn forward_progress(&mut self, raw: &str) {
+        let mut styled = self.parser.clone().parse_line(raw);
+        styled.stream = self.stream;
+        styled.arrived = Some(chrono::Local::now());
+        styled.partial = true;
+        let _ = self.tx.send(styled);
+    }
+
+    fn forward(&mut self, raw: &str) {
+        self.progress = None;
+        let mut styled = self.parser.parse_line(raw);
+        let arrived = chrono::Local::now();
+        styled.stream = self.stream;
+        styled.arrived = Some(arrived);
+        self.logs.write_line(arrived, self.stream, raw, &styled.text);
+        let _ = self.tx.send(styled);
+    }
+}
+
+async fn read_pipe<R>(mut pipe: R, mut forwarder: LineForwarder)
+where
+    R: tokio::io::AsyncRead + Unpin,
+{
+    let mut buf = [0u8; 4096];
+    while let Ok(n) = pipe.read(&mut buf).await {
+        if n == 0 {
+            break;
+        }
+        forwarder.push(&buf[..n]);
+    }
+    forwarder.finish();
+}
+
+/// Writes queued input to the child's stdin until either side goes away.
+async fn write_pipe(mut stdin: tokio::process::ChildStdin, mut input: mpsc::UnboundedReceiver<Vec<u8>>) {
+    while let Some(bytes) = input.recv().await {
+        if stdin.write_all(&bytes).await.is_err() || stdin.flush().await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Writes queued input to the PTY on a thread of its own, as the writer blocks.
+fn write_pty(mut writer: Box<dyn Write + Send>, mut input: mpsc::UnboundedReceiver<Vec<u8>>) {
+    while let Some(bytes) = input.blocking_recv() {
+        if writer.write_all(&bytes).and_then(|_| writer.flush()).is_err() {
+            break;
+        }
+    }
+}
+
+fn read_pty(
+    mut reader: Box<dyn Read + Send>,
+    screen: TerminalScreen,
+    mut forwarder: LineForwarder,
+) {
+    let mut buf = [0u8; 4096];
+    // Linux reports EIO rather than EOF once the child side of the PTY closes
+    while let Ok(n) = reader.read(&mut buf) {
+        if n == 0 {
+            break;
+        }
+        for piece in split_before_private_modes(&buf[..n]) {
+            screen.process(piece);
+            // Full-screen redraws only mean something on the emulated screen, so
+            // keep them out of the line buffer and the log. A line left unfinished
+            // before the switch, like a prompt, is still kept.
+            if screen.in_alternate_screen() {
+                forwarder.finish();
+            } else {
+                forwarder.push(piece);
+            }
+        }
+    }
+    forwarder.finish();
+}
+
+/// Splits a chunk just before each DEC private mode sequence (`ESC [ ?`), so
+/// that each piece is entirely on or off the alternate screen.
+fn split_before_private_modes(chunk: &[u8]) -> Vec<&[u8]> {
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    for (i, window) in chunk.windows(3).enumerate() {
+        if window == b"\x1b[?" && i > start {
+            pieces.push(&chunk[start..i]);
+            start = i;
+        }
+    }
+    pieces.push(&chunk[start..]);
+    pieces
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::output_log::OutputLog;
     use std::fs;
     use tempfile::NamedTempFile;
 
+    fn shell_options(mode: OutputMode) -> SpawnOptions {
+        SpawnOptions { mode, shell: Some("sh".to_string()), ..SpawnOptions::default() }
+    }
+
     #[tokio::test]
     async fn test_logging_to_file() {
         // Create a temporary file for logging
@@ -98,25 +507,353 @@ mod tests {
         let log_path = temp_file.path().to_path_buf();
 
         // Create a simple command that outputs to stdout
-        let mut process_manager = ProcessManager::new(&["echo".to_string(), "test output".to_string()]).unwrap();
+        let mut process_manager = ProcessManager::new(
+            &["echo".to_string(), "test output".to_string()],
+            &SpawnOptions::default(),
+        ).unwrap();
 
         // Open the log file
-        let log_file = Some(Arc::new(std::sync::Mutex::new(
-            std::fs::OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(&log_path)
-                .unwrap()
-        )));
+        let logs = OutputLogs { log: Some(OutputLog::open(&log_path, false).unwrap()), stderr_log: None };
 
         // Start output reading with logging
-        let _rx = process_manager.start_output_reading(log_file);
+        let mut rx = process_manager.start_output_reading(logs);
 
-        // Wait for the process to finish
+        // Wait for the process to finish and the readers to drain
         let _status = process_manager.wait().await.unwrap();
+        while rx.recv().await.is_some() {}
 
         // Check that the log file contains the output
         let log_contents = fs::read_to_string(&log_path).unwrap();
         assert!(log_contents.contains("test output"));
     }
+
+    #[tokio::test]
+    async fn test_lines_are_tagged_with_their_stream() {
+        let mut process_manager =
+            ProcessManager::new(&["echo out; echo err >&2".to_string()], &shell_options(OutputMode::Pipes)).unwrap();
+        let mut rx = process_manager.start_output_reading(OutputLogs::default());
+        process_manager.wait().await.unwrap();
+        let mut lines = Vec::new();
+        while let Some(line) = rx.recv().await {
+            lines.push((line.text, line.stream));
+        }
+
+        lines.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(
+            lines,
+            vec![("err".to_string(), OutputStream::Stderr), ("out".to_string(), OutputStream::Stdout)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pty_mode_is_a_terminal() {
+        let options = SpawnOptions { cols: 100, rows: 30, ..shell_options(OutputMode::Pty) };
+        let mut process_manager = ProcessManager::new(
+            &["test -t 1 && echo tty; stty size".to_string()],
+            &options,
+        ).unwrap();
+        let mut rx = process_manager.start_output_reading(OutputLogs::default());
+
+        let status = process_manager.wait().await.unwrap();
+        let mut lines = Vec::new();
+        while let Some(line) = rx.recv().await {
+            lines.push(line.text);
+        }
+
+        assert!(status.success());
+        assert_eq!(lines, vec!["tty", "30 100"]);
+    }
+
+    #[tokio::test]
+    async fn test_pty_output_reaches_the_screen() {
+        let options = SpawnOptions { cols: 40, rows: 10, ..shell_options(OutputMode::Pty) };
+        let mut process_manager = ProcessManager::new(
+            &["printf 'abc\\033[2Dz\\n$ vim'; printf '\\033[?1049hfull screen\\033[?1049l'; echo after".to_string()],
+            &options,
+        ).unwrap();
+        let mut rx = process_manager.start_output_reading(OutputLogs::default());
+        process_manager.wait().await.unwrap();
+        let mut lines = Vec::new();
+        while let Some(line) = rx.recv().await {
+            lines.push(line.text);
+        }
+
+        let screen = process_manager.screen().unwrap().lock();
+        assert_eq!(screen.screen().rows(0, 40).next().unwrap(), "azc");
+        // The alternate screen redraw stays out of the line buffer
+        assert!(!lines.iter().any(|line| line.contains("full screen")));
+        // What was printed before the switch isn't lost with it
+        assert_eq!(lines[lines.len() - 2..], ["$ vim", "after"]);
+    }
+
+    #[tokio::test]
+    async fn test_input_reaches_the_child() {
+        for mode in [OutputMode::Pipes, OutputMode::Pty] {
+            let options = shell_options(mode);
+            let mut process_manager = ProcessManager::new(&["read name; echo \"hi $name\"".to_string()], &options).unwrap();
+            let mut rx = process_manager.start_output_reading(OutputLogs::default());
+            process_manager.write_input(b"druns\r").unwrap();
+            process_manager.wait().await.unwrap();
+            let mut lines = Vec::new();
+            while let Some(line) = rx.recv().await {
+                lines.push(line.text);
+            }
+            assert_eq!(lines.last().map(String::as_str), Some("hi druns"), "{mode:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_input_to_a_child_that_never_reads_does_not_block() {
+        for mode in [OutputMode::Pipes, OutputMode::Pty] {
+            let mut process_manager = ProcessManager::new(&["sleep 1".to_string()], &shell_options(mode)).unwrap();
+            let started = Instant::now();
+            // Far more than a pipe or PTY buffers
+            for _ in 0..256 {
+                let _ = process_manager.write_input(&[b'x'; 4096]);
+            }
+            assert!(started.elapsed() < Duration::from_millis(500), "{mode:?}");
+            process_manager.wait().await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_exit_code_is_preserved() {
+        for mode in [OutputMode::Pipes, OutputMode::Pty] {
+            let options = shell_options(mode);
+            let mut process_manager = ProcessManager::new(&["exit 3".to_string()], &options).unwrap();
+            let status = process_manager.wait().await.unwrap();
+            assert_eq!(status.code(), Some(3));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_arguments_are_passed_verbatim() {
+        let command: Vec<String> = ["printf", "[%s]", "foo bar", "$HOME", "*", "it's"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        for mode in [OutputMode::Pipes, OutputMode::Pty] {
+            let options = SpawnOptions { mode, ..SpawnOptions::default() };
+            let mut process_manager = ProcessManager::new(&command, &options).unwrap();
+            let mut rx = process_manager.start_output_reading(OutputLogs::default());
+            process_manager.wait().await.unwrap();
+            let mut lines = Vec::new();
+            while let Some(line) = rx.recv().await {
+                lines.push(line.text);
+            }
+            assert_eq!(lines, vec!["[foo bar][$HOME][*][it's]"], "{mode:?}");
+        }
+    }
+
+    /// Collects stdout lines up to and including `text`. Stderr is skipped, as
+    /// the shell may report its killed `sleep` there.
+    async fn recv_until(rx: &mut mpsc::UnboundedReceiver<StyledLine>, text: &str) -> Vec<String> {
+        let mut lines = Vec::new();
+        while let Some(line) = rx.recv().await {
+            if line.stream == OutputStream::Stderr {
+                continue;
+            }
+            let done = line.text == text;
+            lines.push(line.text);
+            if done {
+                break;
+            }
+        }
+        lines
+    }
+
+    fn is_running(pid: &str) -> bool {
+        // Killed orphans may linger as zombies if nothing reaps them
+        fs::read_to_string(format!("/proc/{pid}/stat"))
+            .is_ok_and(|stat| stat.rsplit(") ").next().is_some_and(|rest| !rest.starts_with('Z')))
+    }
+
+    /// Signals are delivered asynchronously, so give the process a moment to die.
+    async fn exits_soon(pid: &str) -> bool {
+        for _ in 0..50 {
+            if !is_running(pid) {
+                return true;
+            }
+            sleep(Duration::from_millis(20)).await;
+        }
+        false
+    }
+
+    #[tokio::test]
+    async fn test_stop_lets_the_child_handle_sigterm() {
+        let mut process_manager = ProcessManager::new(
+            &["trap 'echo bye; exit 0' TERM; echo ready; while :; do sleep 0.1; done".to_string()],
+            &shell_options(OutputMode::Pipes),
+        ).unwrap();
+        let mut rx = process_manager.start_output_reading(OutputLogs::default());
+        recv_until(&mut rx, "ready").await;
+
+        let status = process_manager.stop(Duration::from_secs(5)).await.unwrap();
+        assert!(status.success());
+        assert_eq!(recv_until(&mut rx, "bye").await, vec!["bye"]);
+    }
+
+    #[tokio::test]
+    async fn test_stop_escalates_to_sigkill() {
+        let mut process_manager = ProcessManager::new(
+            &["trap '' TERM; echo ready; while :; do sleep 0.1; done".to_string()],
+            &shell_options(OutputMode::Pipes),
+        ).unwrap();
+        let mut rx = process_manager.start_output_reading(OutputLogs::default());
+        recv_until(&mut rx, "ready").await;
+
+        let status = process_manager.stop(Duration::from_millis(200)).await.unwrap();
+        assert_eq!(status.signal(), Some(libc::SIGKILL));
+    }
+
+    #[tokio::test]
+    async fn test_kill_reaches_the_whole_process_group() {
+        for mode in [OutputMode::Pipes, OutputMode::Pty] {
+            let mut process_manager = ProcessManager::new(&["sleep 30 & echo $!; wait".to_string()], &shell_options(mode)).unwrap();
+            let mut rx = process_manager.start_output_reading(OutputLogs::default());
+            let grandchild = rx.recv().await.unwrap().text;
+            assert!(is_running(&grandchild), "{mode:?}");
+
+            process_manager.kill().await.unwrap();
+            process_manager.wait().await.unwrap();
+            assert!(exits_soon(&grandchild).await, "{mode:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stop_leftovers() {
+        let mut process_manager = ProcessManager::new(&["sleep 30 & echo $!".to_string()], &shell_options(OutputMode::Pipes)).unwrap();
+        let mut rx = process_manager.start_output_reading(OutputLogs::default());
+        let orphan = rx.recv().await.unwrap().text;
+        process_manager.wait().await.unwrap();
+        assert!(is_running(&orphan));
+
+        process_manager.stop_leftovers(Duration::from_secs(5)).await;
+        assert!(exits_soon(&orphan).await);
+    }
+
+    #[test]
+    fn test_missing_program_fails_to_spawn() {
+        let command = vec!["druns-no-such-program".to_string()];
+        assert!(ProcessManager::new(&command, &SpawnOptions::default()).is_err());
+    }
+
+    #[test]
+    fn test_command_argv() {
+        let command = vec!["echo".to_string(), "$HOME".to_string()];
+        assert_eq!(command_argv(&command, None), command);
+        assert_eq!(command_argv(&command, Some("/bin/bash")), vec!["/bin/bash", "-c", "echo $HOME"]);
+    }
+
+    #[tokio::test]
+    async fn test_colored_output_is_styled_and_stripped_in_log() {
+        let temp_file = NamedTempFile::new().unwrap();
+        let log_path = temp_file.path().to_path_buf();
+        let mut process_manager = ProcessManager::new(
+            &["printf '\\033[31mred\\033[0m plain\\n'".to_string()],
+            &shell_options(OutputMode::Pipes),
+        ).unwrap();
+        let logs = OutputLogs { log: Some(OutputLog::open(&log_path, false).unwrap()), stderr_log: None };
+        let mut rx = process_manager.start_output_reading(logs);
+
+        process_manager.wait().await.unwrap();
+        let line = rx.recv().await.unwrap();
+        assert_eq!(line.text, "red plain");
+        assert_eq!(line.runs.len(), 1);
+        while rx.recv().await.is_some() {}
+        assert_eq!(fs::read_to_string(&log_path).unwrap(), "red plain\n");
+    }
+
+    #[test]
+    fn test_split_before_private_modes() {
+        let pieces = split_before_private_modes(b"a\x1b[?1049hb\x1b[?1049lc");
+        assert_eq!(pieces, vec![&b"a"[..], b"\x1b[?1049hb", b"\x1b[?1049lc"]);
+        assert_eq!(split_before_private_modes(b"\x1b[?25l"), vec![&b"\x1b[?25l"[..]]);
+    }
+
+    #[test]
+    fn test_line_splitter_buffers_partial_lines() {
+        let mut splitter = LineSplitter::default();
+        assert_eq!(splitter.push(b"hel"), Vec::<String>::new());
+        assert_eq!(splitter.push(b"lo\r\nwor"), vec!["hello"]);
+        assert_eq!(splitter.push(b"ld\n\n"), vec!["world", ""]);
+        assert_eq!(splitter.finish(), None);
+    }
+
+    #[test]
+    fn test_line_splitter_keeps_the_last_redraw() {
+        let mut splitter = LineSplitter::default();
+        assert!(splitter.push(b"10%\r").is_empty());
+        assert_eq!(splitter.progress(), Some("10%".to_string()));
+        assert!(splitter.push(b"50%\r10").is_empty());
+        assert_eq!(splitter.progress(), Some("50%".to_string()));
+        assert_eq!(splitter.push(b"0%\r\n"), vec!["100%"]);
+        assert_eq!(splitter.progress(), None);
+        assert_eq!(splitter.push(b"\x1b[2K\rdone\n"), vec!["done"]);
+    }
+
+    #[test]
+    fn test_line_splitter_treats_the_first_column_as_cr() {
+        let mut splitter = LineSplitter::default();
+        assert!(splitter.push(b"10%\x1b[1G").is_empty());
+        assert_eq!(splitter.progress(), Some("10%".to_string()));
+        // Even when the sequence arrives split across reads
+        assert!(splitter.push(b"50%\x1b[").is_empty());
+        assert!(splitter.push(b"G").is_empty());
+        assert_eq!(splitter.progress(), Some("50%".to_string()));
+        assert_eq!(splitter.push(b"\x1b[2K\x1b[1Gdone\n"), vec!["done"]);
+        // Other columns are left alone
+        assert_eq!(splitter.push(b"a\x1b[5Gb\n"), vec!["a\x1b[5Gb"]);
+    }
+
+    #[test]
+    fn test_line_splitter_forgets_old_redraws() {
+        let mut splitter = LineSplitter::default();
+        for percent in 0..10_000 {
+            // A redraw can arrive split anywhere, including before its `\r`
+            let redraw = format!("downloading {:>5}/10000", percent);
+            let (head, tail) = redraw.split_at(percent % redraw.len());
+            assert!(splitter.push(head.as_bytes()).is_empty());
+            assert!(splitter.push(tail.as_bytes()).is_empty());
+            assert!(splitter.push(b"\r\r").is_empty());
+            assert!(splitter.pending.len() <= 2 * redraw.len(), "{}", splitter.pending.len());
+        }
+        assert_eq!(splitter.progress(), Some("downloading  9999/10000".to_string()));
+        assert_eq!(splitter.push(b"\n"), vec!["downloading  9999/10000"]);
+    }
+
+    #[test]
+    fn test_progress_updates_replace_each_other() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let temp_file = NamedTempFile::new().unwrap();
+        let logs = OutputLogs { log: Some(OutputLog::open(temp_file.path(), false).unwrap()), stderr_log: None };
+        let mut forwarder = LineForwarder::new(OutputStream::Stdout, tx, logs);
+        forwarder.push(b"fetching 10%\r");
+        forwarder.push(b"fetching 10%\r");
+        forwarder.push(b"fetching 60%\rfetching 100%\nok\n");
+        drop(forwarder);
+
+        let mut lines = Vec::new();
+        while let Ok(line) = rx.try_recv() {
+            lines.push((line.text, line.partial));
+        }
+        let expected = [("fetching 10%", true), ("fetching 100%", false), ("ok", false)];
+        assert_eq!(lines, expected.map(|(text, partial)| (text.to_string(), partial)));
+        assert_eq!(fs::read_to_string(temp_file.path()).unwrap(), "fetching 100%\nok\n");
+    }
+
+    #[test]
+    fn test_line_splitter_finish_returns_remainder() {
+        let mut splitter = LineSplitter::default();
+        splitter.push(b"no newline");
+        assert_eq!(splitter.finish(), Some("no newline".to_string()));
+    }
+
+    #[test]
+    fn test_line_splitter_handles_split_utf8() {
+        let mut splitter = LineSplitter::default();
+        let bytes = "héllo\n".as_bytes();
+        assert!(splitter.push(&bytes[..2]).is_empty());
+        assert_eq!(splitter.push(&bytes[2..]), vec!["héllo"]);
+    }
 }
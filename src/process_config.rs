@@ -0,0 +1,104 @@
+use crate::restart::RestartPolicy;
+
+fn default_shell() -> String {
+    "sh".to_string()
+}
+
+fn default_grace_period_secs() -> f64 {
+    5.0
+}
+
+fn default_restart_backoff_ms() -> u64 {
+    1000
+}
+
+fn default_restart_backoff_max_ms() -> u64 {
+    30_000
+}
+
+fn default_crash_loop_restarts() -> u32 {
+    5
+}
+
+fn default_crash_loop_window_secs() -> u64 {
+    60
+}
+
+fn default_network_device() -> String {
+    "any".to_string()
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ProcessConfig {
+    /// Shell used to interpret the command when running with --shell
+    #[serde(default = "default_shell")]
+    pub shell: String,
+    /// Seconds between SIGTERM and SIGKILL when stopping the command
+    #[serde(default = "default_grace_period_secs")]
+    pub grace_period_secs: f64,
+    /// When to start the command again after it exits on its own
+    #[serde(default)]
+    pub restart_policy: RestartPolicy,
+    /// Stop restarting automatically after this many restarts (unlimited if unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_restarts: Option<u32>,
+    /// Delay before the first automatic restart, doubled for each consecutive one
+    #[serde(default = "default_restart_backoff_ms")]
+    pub restart_backoff_ms: u64,
+    #[serde(default = "default_restart_backoff_max_ms")]
+    pub restart_backoff_max_ms: u64,
+    /// Give up when this many automatic restarts happen within the window (0 to never give up)
+    #[serde(default = "default_crash_loop_restarts")]
+    pub crash_loop_restarts: u32,
+    #[serde(default = "default_crash_loop_window_secs")]
+    pub crash_loop_window_secs: u64,
+    /// Stop a run that takes longer than this, e.g. "10m"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<String>,
+    /// Interface the experimental-pcap monitor captures on, or "any" for all of them
+    #[serde(default = "default_network_device")]
+    pub network_device: String,
+}
+
+impl Default for ProcessConfig {
+    fn default() -> Self {
+        ProcessConfig {
+            shell: default_shell(),
+            grace_period_secs: default_grace_period_secs(),
+            restart_policy: RestartPolicy::Never,
+            max_restarts: None,
+            restart_backoff_ms: default_restart_backoff_ms(),
+            restart_backoff_max_ms: default_restart_backoff_max_ms(),
+            crash_loop_restarts: default_crash_loop_restarts(),
+            crash_loop_window_secs: default_crash_loop_window_secs(),
+            timeout: None,
+            network_device: default_network_device(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_config_default() {
+        let config = ProcessConfig::default();
+        assert_eq!(config.shell, "sh");
+        assert_eq!(config.grace_period_secs, 5.0);
+        assert_eq!(config.restart_policy, RestartPolicy::Never);
+        assert_eq!(config.max_restarts, None);
+        assert_eq!(config.restart_backoff_ms, 1000);
+        assert_eq!(config.crash_loop_restarts, 5);
+        assert!(config.timeout.is_none());
+        assert_eq!(config.network_device, "any");
+    }
+
+    #[test]
+    fn test_restart_policy_deserialization() {
+        let config: ProcessConfig = toml::from_str("restart_policy = \"on-failure\"\nmax_restarts = 3").unwrap();
+        assert_eq!(config.restart_policy, RestartPolicy::OnFailure);
+        assert_eq!(config.max_restarts, Some(3));
+        assert_eq!(config.restart_backoff_max_ms, 30_000);
+    }
+}
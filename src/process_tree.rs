@@ -0,0 +1,294 @@
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use sysinfo::{Pid, ProcessStatus, System};
+
+/// `root` and every process descended from it with its depth below `root`,
+/// each parent followed by its children, given each process' parent as
+/// `(pid, ppid)` pairs.
+pub fn tree(root: u32, parents: impl IntoIterator<Item = (u32, u32)>) -> Vec<(u32, usize)> {
+    let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+    for (pid, ppid) in parents {
+        if pid != ppid {
+            children.entry(ppid).or_default().push(pid);
+        }
+    }
+    let mut tree = Vec::new();
+    let mut seen = HashSet::new();
+    let mut stack = vec![(root, 0)];
+    while let Some((pid, depth)) = stack.pop() {
+        // A PID reused while we read /proc could otherwise make a loop
+        if !seen.insert(pid) {
+            continue;
+        }
+        tree.push((pid, depth));
+        if let Some(children) = children.get_mut(&pid) {
+            children.sort_unstable_by_key(|&child| Reverse(child));
+            stack.extend(children.iter().map(|&child| (child, depth + 1)));
+        }
+    }
+    tree
+}
+
+/// `root` and every process descended from it, parents before children.
+pub fn walk(root: u32, parents: impl IntoIterator<Item = (u32, u32)>) -> Vec<u32> {
+    tree(root, parents).into_iter().map(|(pid, _)| pid).collect()
+}
+
+/// `root` and its descendants, from the parent of every process in /proc.
+/// Threads aren't listed at the top of /proc, so only processes are found.
+pub fn descendants(root: u32) -> Vec<u32> {
+    let parents = procfs::process::all_processes()
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|process| process.stat().ok())
+        .map(|stat| (stat.pid as u32, stat.ppid as u32));
+    walk(root, parents)
+}
+
+/// Signals the process panel can send to a single process, by key.
+pub const PANEL_SIGNALS: [(char, i32, &str); 5] = [
+    ('T', libc::SIGTERM, "SIGTERM"),
+    ('K', libc::SIGKILL, "SIGKILL"),
+    ('S', libc::SIGSTOP, "SIGSTOP"),
+    ('C', libc::SIGCONT, "SIGCONT"),
+    ('U', libc::SIGUSR1, "SIGUSR1"),
+];
+
+/// Sends `signal` to `pid` alone, as long as it is still in `root`'s tree;
+/// a PID that has been reused since the panel was drawn is left alone.
+pub fn signal_descendant(root: u32, pid: u32, signal: i32) -> std::io::Result<()> {
+    if !descendants(root).contains(&pid) {
+        return Err(std::io::Error::new(std::io::ErrorKind::NotFound, "no longer part of the command"));
+    }
+    if unsafe { libc::kill(pid as libc::pid_t, signal) } == 0 {
+        Ok(())
+    } else {
+        Err(std::io::Error::last_os_error())
+    }
+}
+
+/// One process of a command's tree, as the process panel lists it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessRow {
+    pub pid: u32,
+    /// How far below the command's own process it is
+    pub depth: usize,
+    pub command: String,
+    pub state: &'static str,
+    pub cpu_percent: f32,
+    /// Resident memory in bytes
+    pub memory: u64,
+    pub threads: usize,
+    pub runtime: Duration,
+}
+
+impl ProcessRow {
+    /// `root` and its descendants in an already refreshed sysinfo snapshot,
+    /// in tree order. sysinfo lists threads as processes of their own; they
+    /// are counted in `threads` instead.
+    pub fn list(system: &System, root: u32) -> Vec<ProcessRow> {
+        let parents = system
+            .processes()
+            .values()
+            .filter(|process| process.thread_kind().is_none())
+            .filter_map(|process| Some((process.pid().as_u32(), process.parent()?.as_u32())));
+        tree(root, parents)
+            .into_iter()
+            .filter_map(|(pid, depth)| {
+                let process = system.process(Pid::from_u32(pid))?;
+                let command = match process.cmd() {
+                    [] => process.name().to_string(),
+                    cmd => cmd.join(" "),
+                };
+                Some(ProcessRow {
+                    pid,
+                    depth,
+                    command,
+                    state: state_name(process.status()),
+                    cpu_percent: process.cpu_usage(),
+                    memory: process.memory(),
+                    threads: process.tasks().map_or(1, |tasks| tasks.len().max(1)),
+                    runtime: Duration::from_secs(process.run_time()),
+                })
+            })
+            .collect()
+    }
+}
+
+/// A short name for a process state, like `ps` shows.
+fn state_name(status: ProcessStatus) -> &'static str {
+    match status {
+        ProcessStatus::Run => "run",
+        ProcessStatus::Sleep => "sleep",
+        ProcessStatus::Idle => "idle",
+        ProcessStatus::UninterruptibleDiskSleep => "disk",
+        ProcessStatus::Stop => "stop",
+        ProcessStatus::Tracing => "trace",
+        ProcessStatus::Zombie => "zombie",
+        ProcessStatus::Dead => "dead",
+        _ => "?",
+    }
+}
+
+/// The column the process panel is sorted by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TreeSort {
+    /// Parents followed by their children
+    #[default]
+    Tree,
+    Cpu,
+    Memory,
+    Runtime,
+    Pid,
+    State,
+    Command,
+}
+
+impl TreeSort {
+    /// The column the sort key switches to next.
+    pub fn next(self) -> Self {
+        match self {
+            TreeSort::Tree => TreeSort::Cpu,
+            TreeSort::Cpu => TreeSort::Memory,
+            TreeSort::Memory => TreeSort::Runtime,
+            TreeSort::Runtime => TreeSort::Pid,
+            TreeSort::Pid => TreeSort::State,
+            TreeSort::State => TreeSort::Command,
+            TreeSort::Command => TreeSort::Tree,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            TreeSort::Tree => "tree",
+            TreeSort::Cpu => "cpu",
+            TreeSort::Memory => "memory",
+            TreeSort::Runtime => "runtime",
+            TreeSort::Pid => "pid",
+            TreeSort::State => "state",
+            TreeSort::Command => "command",
+        }
+    }
+
+    /// Sorts `rows`, which are in tree order. The heaviest processes come
+    /// first for CPU, memory and runtime.
+    pub fn sort(self, rows: &mut [ProcessRow]) {
+        match self {
+            TreeSort::Tree => {}
+            TreeSort::Cpu => rows.sort_by(|a, b| b.cpu_percent.total_cmp(&a.cpu_percent)),
+            TreeSort::Memory => rows.sort_by_key(|row| Reverse(row.memory)),
+            TreeSort::Runtime => rows.sort_by_key(|row| Reverse(row.runtime)),
+            TreeSort::Pid => rows.sort_by_key(|row| row.pid),
+            TreeSort::State => rows.sort_by_key(|row| row.state),
+            TreeSort::Command => rows.sort_by(|a, b| a.command.cmp(&b.command)),
+        }
+    }
+}
+
+/// What a whole process tree uses, summed over its processes.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TreeUsage {
+    pub processes: usize,
+    pub threads: usize,
+    pub cpu_percent: f32,
+    pub memory: u64,
+}
+
+impl TreeUsage {
+    pub fn of(rows: &[ProcessRow]) -> Self {
+        TreeUsage {
+            processes: rows.len(),
+            threads: rows.iter().map(|row| row.threads).sum(),
+            cpu_percent: rows.iter().map(|row| row.cpu_percent).sum(),
+            memory: rows.iter().map(|row| row.memory).sum(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::{Command, Stdio};
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_walk_finds_every_descendant() {
+        // 1 ─ 10 ─ 11 ─ 12, 1 ─ 20, and 30 outside the tree
+        let parents = [(20, 1), (11, 10), (10, 1), (12, 11), (30, 2), (1, 0)];
+        assert_eq!(tree(1, parents), vec![(1, 0), (10, 1), (11, 2), (12, 3), (20, 1)]);
+        assert_eq!(walk(1, parents), vec![1, 10, 11, 12, 20]);
+        assert_eq!(walk(11, parents), vec![11, 12]);
+        assert_eq!(walk(99, parents), vec![99]);
+    }
+
+    #[test]
+    fn test_walk_survives_a_loop() {
+        assert_eq!(walk(1, [(2, 1), (1, 2), (3, 3)]), vec![1, 2]);
+    }
+
+    fn row(pid: u32, command: &str, cpu_percent: f32, memory: u64) -> ProcessRow {
+        ProcessRow {
+            pid,
+            depth: 0,
+            command: command.to_string(),
+            state: "run",
+            cpu_percent,
+            memory,
+            threads: 2,
+            runtime: Duration::from_secs(pid as u64),
+        }
+    }
+
+    #[test]
+    fn test_sort_and_usage() {
+        let mut rows = vec![row(1, "make -j8", 0.5, 4096), row(2, "cc1 big.c", 98.0, 1024), row(3, "cc1 small.c", 40.0, 8192)];
+        let pids = |rows: &[ProcessRow]| rows.iter().map(|row| row.pid).collect::<Vec<_>>();
+        TreeSort::Cpu.sort(&mut rows);
+        assert_eq!(pids(&rows), vec![2, 3, 1]);
+        TreeSort::Memory.sort(&mut rows);
+        assert_eq!(pids(&rows), vec![3, 1, 2]);
+        TreeSort::Command.sort(&mut rows);
+        assert_eq!(pids(&rows), vec![2, 3, 1]);
+        TreeSort::Pid.sort(&mut rows);
+        assert_eq!(pids(&rows), vec![1, 2, 3]);
+
+        let usage = TreeUsage::of(&rows);
+        assert_eq!(usage, TreeUsage { processes: 3, threads: 6, cpu_percent: 138.5, memory: 13312 });
+    }
+
+    #[test]
+    fn test_descendants_includes_grandchildren() {
+        let mut child = Command::new("sh")
+            .args(["-c", "sh -c 'sleep 5; true' & wait"])
+            .stdout(Stdio::null())
+            .spawn()
+            .unwrap();
+        let root = child.id();
+        let started = Instant::now();
+        let mut tree = descendants(root);
+        // Until the shells have started the sleep
+        while tree.len() < 3 && started.elapsed() < Duration::from_secs(5) {
+            std::thread::sleep(Duration::from_millis(20));
+            tree = descendants(root);
+        }
+        for &pid in &tree {
+            unsafe { libc::kill(pid as i32, libc::SIGKILL) };
+        }
+        let _ = child.wait();
+        assert_eq!(tree.len(), 3, "{tree:?}");
+        assert_eq!(tree[0], root);
+    }
+
+    #[test]
+    fn test_signal_only_reaches_the_tree() {
+        let mut child = Command::new("sleep").arg("5").spawn().unwrap();
+        let mut other = Command::new("sleep").arg("5").spawn().unwrap();
+        assert!(signal_descendant(child.id(), other.id(), libc::SIGKILL).is_err());
+        signal_descendant(child.id(), child.id(), libc::SIGKILL).unwrap();
+        assert_eq!(std::os::unix::process::ExitStatusExt::signal(&child.wait().unwrap()), Some(libc::SIGKILL));
+        other.kill().unwrap();
+        other.wait().unwrap();
+    }
+}
@@ -0,0 +1,101 @@
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// One command of a multi-process run, from a Procfile line or a
+/// `[[process]]` table in the config file. The command is run by the shell.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProcessDefinition {
+    pub name: String,
+    pub command: String,
+}
+
+/// Parses Procfile lines of the form `name: command`. Blank lines and
+/// `#` comments are skipped.
+pub fn parse(contents: &str) -> Result<Vec<ProcessDefinition>, String> {
+    let mut definitions = Vec::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line_number = index + 1;
+        let (name, command) = line
+            .split_once(':')
+            .ok_or_else(|| format!("line {line_number}: expected \"name: command\""))?;
+        let definition = ProcessDefinition { name: name.trim().to_string(), command: command.trim().to_string() };
+        validate(&definition).map_err(|e| format!("line {line_number}: {e}"))?;
+        definitions.push(definition);
+    }
+    check_unique(&definitions)?;
+    Ok(definitions)
+}
+
+pub fn load<P: AsRef<Path>>(path: P) -> Result<Vec<ProcessDefinition>, Box<dyn std::error::Error>> {
+    let path = path.as_ref();
+    let contents = std::fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {e}", path.display()))?;
+    Ok(parse(&contents).map_err(|e| format!("{}: {e}", path.display()))?)
+}
+
+/// Checks a definition, wherever it came from.
+pub fn validate(definition: &ProcessDefinition) -> Result<(), String> {
+    let name = &definition.name;
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.') {
+        return Err(format!("invalid process name {name:?}"));
+    }
+    if definition.command.trim().is_empty() {
+        return Err(format!("process {name:?} has no command"));
+    }
+    Ok(())
+}
+
+/// Names label the panes and log lines, so two processes can't share one.
+pub fn check_unique(definitions: &[ProcessDefinition]) -> Result<(), String> {
+    for (i, definition) in definitions.iter().enumerate() {
+        if definitions[..i].iter().any(|earlier| earlier.name == definition.name) {
+            return Err(format!("process {:?} is defined twice", definition.name));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn definition(name: &str, command: &str) -> ProcessDefinition {
+        ProcessDefinition { name: name.to_string(), command: command.to_string() }
+    }
+
+    #[test]
+    fn test_parse_procfile() {
+        let contents = "# local stack\nweb: bundle exec rails s -p $PORT\n\n  worker:   sidekiq -c 5  \n";
+        assert_eq!(
+            parse(contents).unwrap(),
+            vec![definition("web", "bundle exec rails s -p $PORT"), definition("worker", "sidekiq -c 5")]
+        );
+    }
+
+    #[test]
+    fn test_command_may_contain_colons() {
+        let definitions = parse("api: env URL=http://localhost:3000 ./api").unwrap();
+        assert_eq!(definitions[0].command, "env URL=http://localhost:3000 ./api");
+    }
+
+    #[test]
+    fn test_parse_errors_name_the_line() {
+        assert_eq!(parse("web: ./web\nnonsense").unwrap_err(), "line 2: expected \"name: command\"");
+        assert_eq!(parse("we b: ./web").unwrap_err(), "line 1: invalid process name \"we b\"");
+        assert_eq!(parse("web:").unwrap_err(), "line 1: process \"web\" has no command");
+    }
+
+    #[test]
+    fn test_duplicate_names_are_rejected() {
+        assert_eq!(parse("web: a\nweb: b").unwrap_err(), "process \"web\" is defined twice");
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let error = load("/nonexistent/Procfile").unwrap_err().to_string();
+        assert!(error.starts_with("Cannot read /nonexistent/Procfile"));
+    }
+}
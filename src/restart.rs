@@ -0,0 +1,211 @@
+use std::collections::VecDeque;
+use std::process::ExitStatus;
+use std::time::{Duration, Instant};
+
+/// When a command that exited on its own is started again automatically.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartPolicy {
+    /// Leave it exited
+    #[default]
+    Never,
+    /// Restart when it exits with a non-zero code or is killed by a signal
+    OnFailure,
+    /// Restart whenever it exits
+    Always,
+}
+
+impl RestartPolicy {
+    pub fn name(self) -> &'static str {
+        match self {
+            RestartPolicy::Never => "never",
+            RestartPolicy::OnFailure => "on-failure",
+            RestartPolicy::Always => "always",
+        }
+    }
+
+    pub fn applies(self, status: &ExitStatus) -> bool {
+        match self {
+            RestartPolicy::Never => false,
+            RestartPolicy::OnFailure => !status.success(),
+            RestartPolicy::Always => true,
+        }
+    }
+}
+
+/// Bounds on automatic restarts.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RestartLimits {
+    /// Give up after this many automatic restarts; unlimited when `None`
+    pub max_restarts: Option<u32>,
+    /// Delay before the first restart, doubled for each consecutive one
+    pub backoff: Duration,
+    pub backoff_max: Duration,
+    /// Give up once this many restarts happen within `crash_loop_window`; 0 disables
+    pub crash_loop_restarts: u32,
+    /// A run that lasts this long counts as healthy and resets the backoff
+    pub crash_loop_window: Duration,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RestartDecision {
+    /// Start the command again after this delay
+    Restart(Duration),
+    /// The policy wants a restart but a limit was hit; says which
+    GiveUp(String),
+    /// The policy doesn't restart this exit
+    Stay,
+}
+
+/// Applies the restart policy to one process' exits, keeping the state
+/// backoff and crash-loop detection need.
+#[derive(Debug, Clone)]
+pub struct RestartTracker {
+    policy: RestartPolicy,
+    limits: RestartLimits,
+    /// Automatic restarts so far
+    attempts: u32,
+    /// Automatic restarts since the last healthy run, for the backoff
+    consecutive: u32,
+    /// When recent automatic restarts happened, for crash-loop detection
+    recent: VecDeque<Instant>,
+}
+
+impl RestartTracker {
+    pub fn new(policy: RestartPolicy, limits: RestartLimits) -> Self {
+        RestartTracker { policy, limits, attempts: 0, consecutive: 0, recent: VecDeque::new() }
+    }
+
+    pub fn policy(&self) -> RestartPolicy {
+        self.policy
+    }
+
+    /// Decides what to do about a run that lasted `uptime` and ended with `status`.
+    pub fn decide(&mut self, status: &ExitStatus, uptime: Duration, now: Instant) -> RestartDecision {
+        if !self.policy.applies(status) {
+            return RestartDecision::Stay;
+        }
+        let window = self.limits.crash_loop_window;
+        if uptime >= window {
+            self.consecutive = 0;
+        }
+        self.recent.retain(|&at| now.duration_since(at) < window);
+
+        if let Some(max_restarts) = self.limits.max_restarts {
+            if self.attempts >= max_restarts {
+                return RestartDecision::GiveUp(format!("reached the limit of {max_restarts} restarts"));
+            }
+        }
+        let crash_loop_restarts = self.limits.crash_loop_restarts;
+        if crash_loop_restarts > 0 && self.recent.len() >= crash_loop_restarts as usize {
+            return RestartDecision::GiveUp(format!(
+                "crash loop ({crash_loop_restarts} restarts within {}s)",
+                window.as_secs()
+            ));
+        }
+
+        let delay = self
+            .limits
+            .backoff
+            .saturating_mul(2u32.saturating_pow(self.consecutive))
+            .min(self.limits.backoff_max);
+        self.attempts += 1;
+        self.consecutive += 1;
+        self.recent.push_back(now);
+        RestartDecision::Restart(delay)
+    }
+
+    /// Forgets the backoff and crash history, e.g. after a manual restart.
+    pub fn reset(&mut self) {
+        self.consecutive = 0;
+        self.recent.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::process::ExitStatusExt;
+
+    fn limits() -> RestartLimits {
+        RestartLimits {
+            max_restarts: None,
+            backoff: Duration::from_secs(1),
+            backoff_max: Duration::from_secs(5),
+            crash_loop_restarts: 0,
+            crash_loop_window: Duration::from_secs(60),
+        }
+    }
+
+    fn exit(code: i32) -> ExitStatus {
+        ExitStatus::from_raw(code << 8)
+    }
+
+    #[test]
+    fn test_policies() {
+        let killed = ExitStatus::from_raw(libc::SIGKILL);
+        assert!(!RestartPolicy::Never.applies(&exit(1)));
+        assert!(RestartPolicy::OnFailure.applies(&exit(1)));
+        assert!(RestartPolicy::OnFailure.applies(&killed));
+        assert!(!RestartPolicy::OnFailure.applies(&exit(0)));
+        assert!(RestartPolicy::Always.applies(&exit(0)));
+    }
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_maximum() {
+        let mut tracker = RestartTracker::new(RestartPolicy::Always, limits());
+        let now = Instant::now();
+        let delays: Vec<_> = (0..5).map(|_| tracker.decide(&exit(1), Duration::ZERO, now)).collect();
+        let secs = |s| RestartDecision::Restart(Duration::from_secs(s));
+        assert_eq!(delays, vec![secs(1), secs(2), secs(4), secs(5), secs(5)]);
+
+        // A run that stays up resets the backoff
+        assert_eq!(tracker.decide(&exit(1), Duration::from_secs(120), now), secs(1));
+    }
+
+    #[test]
+    fn test_success_is_left_alone_on_failure_policy() {
+        let mut tracker = RestartTracker::new(RestartPolicy::OnFailure, limits());
+        assert_eq!(tracker.decide(&exit(0), Duration::ZERO, Instant::now()), RestartDecision::Stay);
+    }
+
+    #[test]
+    fn test_max_restarts() {
+        let mut tracker = RestartTracker::new(RestartPolicy::Always, RestartLimits { max_restarts: Some(2), ..limits() });
+        let now = Instant::now();
+        assert!(matches!(tracker.decide(&exit(1), Duration::ZERO, now), RestartDecision::Restart(_)));
+        assert!(matches!(tracker.decide(&exit(1), Duration::ZERO, now), RestartDecision::Restart(_)));
+        assert_eq!(
+            tracker.decide(&exit(1), Duration::ZERO, now),
+            RestartDecision::GiveUp("reached the limit of 2 restarts".to_string())
+        );
+    }
+
+    #[test]
+    fn test_crash_loop_detection() {
+        let limits = RestartLimits { crash_loop_restarts: 3, crash_loop_window: Duration::from_secs(10), ..limits() };
+        let mut tracker = RestartTracker::new(RestartPolicy::OnFailure, limits);
+        let start = Instant::now();
+        for i in 0..3 {
+            let decision = tracker.decide(&exit(1), Duration::ZERO, start + Duration::from_secs(i));
+            assert!(matches!(decision, RestartDecision::Restart(_)));
+        }
+        assert_eq!(
+            tracker.decide(&exit(1), Duration::ZERO, start + Duration::from_secs(3)),
+            RestartDecision::GiveUp("crash loop (3 restarts within 10s)".to_string())
+        );
+        // Restarts spread further apart than the window are fine
+        let decision = tracker.decide(&exit(1), Duration::ZERO, start + Duration::from_secs(30));
+        assert!(matches!(decision, RestartDecision::Restart(_)));
+    }
+
+    #[test]
+    fn test_reset_clears_the_backoff() {
+        let mut tracker = RestartTracker::new(RestartPolicy::Always, limits());
+        let now = Instant::now();
+        tracker.decide(&exit(1), Duration::ZERO, now);
+        tracker.decide(&exit(1), Duration::ZERO, now);
+        tracker.reset();
+        assert_eq!(tracker.decide(&exit(1), Duration::ZERO, now), RestartDecision::Restart(Duration::from_secs(1)));
+    }
+}
@@ -0,0 +1,371 @@
+use crate::ansi::{AnsiParser, OutputStream, StyledLine};
+use crate::json_log::JsonLogConfig;
+use crate::search::{LineFilter, Search};
+use crate::ui_output::{OutputView, StreamFilter};
+use chrono::{Local, TimeZone};
+use std::borrow::Cow;
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::os::unix::fs::FileExt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A process' output: the most recent lines in a ring buffer, and in
+/// unlimited mode the older ones paged out to a temporary file.
+pub struct Scrollback {
+    lines: VecDeque<StyledLine>,
+    capacity: usize,
+    unlimited: bool,
+    /// Lines paged out of the ring buffer; they come before `lines`
+    spill: Option<SpillFile>,
+    /// Lines dropped from the front, neither in memory nor on disk
+    dropped: usize,
+}
+
+impl Scrollback {
+    /// Keeps `capacity` lines in memory. Older lines are dropped, or paged
+    /// out to disk when `unlimited`.
+    pub fn new(capacity: usize, unlimited: bool) -> Self {
+        let capacity = capacity.max(1);
+        Scrollback { lines: VecDeque::with_capacity(capacity.min(4096)), capacity, unlimited, spill: None, dropped: 0 }
+    }
+
+    pub fn push(&mut self, line: StyledLine) {
+        if self.lines.len() == self.capacity {
+            let oldest = self.lines.pop_front().expect("capacity is at least 1");
+            if self.unlimited && self.spill.is_none() {
+                // Without a temporary file the oldest lines are dropped instead
+                self.spill = SpillFile::create().ok();
+                self.unlimited = self.spill.is_some();
+            }
+            if !self.spill.as_mut().is_some_and(|spill| spill.push(&oldest)) {
+                self.dropped += 1;
+            }
+        }
+        self.lines.push_back(line);
+    }
+
+    /// Lines paged out to disk, which come before the ones in memory.
+    pub fn spilled(&self) -> usize {
+        self.spill.as_ref().map_or(0, SpillFile::len)
+    }
+
+    pub fn len(&self) -> usize {
+        self.spilled() + self.lines.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The id of the line at `index`: how many lines were pushed before it.
+    /// Unlike its index, it stays the same as older lines are dropped.
+    pub fn id(&self, index: usize) -> usize {
+        self.dropped + index
+    }
+
+    /// The index of the line with `id`, while it is still kept.
+    pub fn index_of(&self, id: usize) -> Option<usize> {
+        id.checked_sub(self.dropped).filter(|&index| index < self.len())
+    }
+
+    pub fn get(&self, index: usize) -> Option<Cow<'_, StyledLine>> {
+        let spilled = self.spilled();
+        if index < spilled {
+            self.spill.as_ref()?.get(index).map(Cow::Owned)
+        } else {
+            self.lines.get(index - spilled).map(Cow::Borrowed)
+        }
+    }
+
+    /// Puts `line` in place of the line with `id`, unless that one has left memory.
+    pub fn replace(&mut self, id: usize, line: StyledLine) -> Result<(), StyledLine> {
+        let in_memory = id.checked_sub(self.dropped + self.spilled()).and_then(|index| self.lines.get_mut(index));
+        match in_memory {
+            Some(old) => {
+                *old = line;
+                Ok(())
+            }
+            None => Err(line),
+        }
+    }
+
+    pub fn last_mut(&mut self) -> Option<&mut StyledLine> {
+        self.lines.back_mut()
+    }
+
+    /// The lines still held in memory, oldest first.
+    pub fn in_memory(&self) -> impl DoubleEndedIterator<Item = &StyledLine> {
+        self.lines.iter()
+    }
+
+    /// The last `count` lines, oldest first.
+    pub fn tail(&self, count: usize) -> Vec<Cow<'_, StyledLine>> {
+        let len = self.len();
+        (len.saturating_sub(count)..len).filter_map(|index| self.get(index)).collect()
+    }
+
+    /// The id of the oldest line in memory, or of the next line pushed.
+    fn first_in_memory(&self) -> usize {
+        self.id(self.spilled())
+    }
+}
+
+/// Which lines of a scrollback a view shows, in order, and which of them
+/// match the search. It is kept up to date a line at a time as output comes
+/// in, so that drawing doesn't go through the whole buffer. Lines paged out
+/// to disk are only shown while nothing is filtered, and never searched.
+#[derive(Debug, Default)]
+pub struct ShownLines {
+    streams: StreamFilter,
+    filter: Option<LineFilter>,
+    json: Option<JsonLogConfig>,
+    search: Option<Search>,
+    /// Ids of the lines shown; `None` while every line is
+    ids: Option<VecDeque<usize>>,
+    /// Ids of the shown lines in memory that match the search
+    matches: VecDeque<usize>,
+}
+
+impl ShownLines {
+    /// The lines of `output` that `view` shows, and the ones `search` matches.
+    pub fn new(output: &Scrollback, view: OutputView, search: Option<&Search>) -> Self {
+        let mut shown = ShownLines {
+            streams: view.streams,
+            filter: view.filter.cloned(),
+            json: view.json.cloned(),
+            search: search.cloned(),
+            ids: (!view.is_unfiltered()).then(VecDeque::new),
+            matches: VecDeque::new(),
+        };
+        for (index, line) in output.lines.iter().enumerate() {
+            shown.add(output.id(output.spilled() + index), line);
+        }
+        shown
+    }
+
+    /// Takes in the line with `id` after it was pushed to `output`, or
+    /// replaced there.
+    pub fn update(&mut self, output: &Scrollback, id: usize) {
+        let first = output.first_in_memory();
+        for ids in self.ids.iter_mut().chain([&mut self.matches]) {
+            while ids.front().is_some_and(|&oldest| oldest < first) {
+                ids.pop_front();
+            }
+            if let Ok(position) = ids.binary_search(&id) {
+                ids.remove(position);
+            }
+        }
+        if let Some(line) = id.checked_sub(first).and_then(|index| output.lines.get(index)) {
+            self.add(id, line);
+        }
+    }
+
+    /// Adds the line with `id` if it is shown, after any lines it comes after.
+    fn add(&mut self, id: usize, line: &StyledLine) {
+        let view = OutputView { streams: self.streams, filter: self.filter.as_ref(), json: self.json.as_ref() };
+        let line = view.as_shown(line);
+        let insert = |ids: &mut VecDeque<usize>| ids.insert(ids.partition_point(|&other| other < id), id);
+        match &mut self.ids {
+            Some(ids) if view.shows(&line) => insert(ids),
+            Some(_) => return,
+            None => {}
+        }
+        if self.search.as_ref().is_some_and(|search| search.is_match(&line)) {
+            insert(&mut self.matches);
+        }
+    }
+
+    pub fn len(&self, output: &Scrollback) -> usize {
+        self.ids.as_ref().map_or(output.len(), VecDeque::len)
+    }
+
+    /// The scrollback index of the line at `position`.
+    pub fn index(&self, output: &Scrollback, position: usize) -> Option<usize> {
+        match &self.ids {
+            Some(ids) => output.index_of(*ids.get(position)?),
+            None => (position < output.len()).then_some(position),
+        }
+    }
+
+    /// The position of the line with `id`, if it is shown.
+    pub fn position(&self, output: &Scrollback, id: usize) -> Option<usize> {
+        match &self.ids {
+            Some(ids) => ids.binary_search(&id).ok(),
+            None => output.index_of(id),
+        }
+    }
+
+    /// Ids of the lines the search matches, oldest first.
+    pub fn matches(&self) -> &VecDeque<usize> {
+        &self.matches
+    }
+
+    pub fn is_match(&self, id: usize) -> bool {
+        self.matches.binary_search(&id).is_ok()
+    }
+}
+
+/// Lines paged out of memory, one per line of an unlinked temporary file as
+/// `stream <tab> arrival in ms <tab> repeats <tab> text with SGR sequences`.
+struct SpillFile {
+    file: File,
+    /// Where each line starts
+    offsets: Vec<u64>,
+    end: u64,
+}
+
+static SPILL_FILES: AtomicUsize = AtomicUsize::new(0);
+
+impl SpillFile {
+    fn create() -> std::io::Result<Self> {
+        let number = SPILL_FILES.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("druns-scrollback-{}-{number}", std::process::id()));
+        let file = OpenOptions::new().read(true).write(true).create_new(true).open(&path)?;
+        // The open handle keeps it around; nothing is left behind when druns exits
+        std::fs::remove_file(&path)?;
+        Ok(SpillFile { file, offsets: Vec::new(), end: 0 })
+    }
+
+    fn len(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Appends `line`, returning whether it could be written.
+    fn push(&mut self, line: &StyledLine) -> bool {
+        let stream = match line.stream {
+            OutputStream::Stdout => 'o',
+            OutputStream::Stderr => 'e',
+            OutputStream::Druns => 'd',
+        };
+        let arrived = line.arrived.map_or(String::new(), |arrived| arrived.timestamp_millis().to_string());
+        let record = format!("{stream}\t{arrived}\t{}\t{}\n", line.repeats, line.to_ansi());
+        // A line that can't be written is dropped, as without unlimited scrollback,
+        // and the next one goes where it would have started
+        let written = self.file.write_all_at(record.as_bytes(), self.end).is_ok();
+        if written {
+            self.offsets.push(self.end);
+            self.end += record.len() as u64;
+        }
+        written
+    }
+
+    fn get(&self, index: usize) -> Option<StyledLine> {
+        let start = *self.offsets.get(index)?;
+        let end = self.offsets.get(index + 1).copied().unwrap_or(self.end);
+        let mut buf = vec![0; (end - start) as usize];
+        self.file.read_exact_at(&mut buf, start).ok()?;
+        let record = String::from_utf8_lossy(&buf);
+        let mut fields = record.trim_end_matches('\n').splitn(4, '\t');
+        let stream = match fields.next()? {
+            "e" => OutputStream::Stderr,
+            "d" => OutputStream::Druns,
+            _ => OutputStream::Stdout,
+        };
+        let arrived = fields.next()?.parse().ok().and_then(|millis| Local.timestamp_millis_opt(millis).single());
+        let repeats = fields.next()?.parse().unwrap_or(0);
+        let mut line = AnsiParser::new().parse_line(fields.next()?);
+        line.stream = stream;
+        line.arrived = arrived;
+        line.repeats = repeats;
+        Some(line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn texts<'a>(lines: impl IntoIterator<Item = Cow<'a, StyledLine>>) -> Vec<String> {
+        lines.into_iter().map(|line| line.text.clone()).collect()
+    }
+
+    fn filled(capacity: usize, unlimited: bool, count: usize) -> Scrollback {
+        let mut scrollback = Scrollback::new(capacity, unlimited);
+        for i in 0..count {
+            scrollback.push(StyledLine::plain(format!("line {i}")));
+        }
+        scrollback
+    }
+
+    #[test]
+    fn test_bounded_drops_the_oldest_lines() {
+        let scrollback = filled(3, false, 5);
+        assert_eq!(scrollback.len(), 3);
+        assert_eq!(scrollback.spilled(), 0);
+        assert_eq!(texts(scrollback.tail(10)), vec!["line 2", "line 3", "line 4"]);
+        // Ids go on counting from the first line pushed
+        assert_eq!(scrollback.id(0), 2);
+        assert_eq!(scrollback.index_of(4), Some(2));
+        assert_eq!(scrollback.index_of(1), None);
+        assert_eq!(scrollback.index_of(5), None);
+    }
+
+    #[test]
+    fn test_unlimited_pages_old_lines_to_disk() {
+        let mut scrollback = Scrollback::new(2, true);
+        let mut first = AnsiParser::new().parse_line("\x1b[31mred\x1b[0m first");
+        first.stream = OutputStream::Stderr;
+        first.arrived = Local.timestamp_millis_opt(1_700_000_000_123).single();
+        first.repeats = 4;
+        scrollback.push(first.clone());
+        for i in 1..5 {
+            scrollback.push(StyledLine::plain(format!("line {i}")));
+        }
+        assert_eq!(scrollback.len(), 5);
+        assert_eq!(scrollback.spilled(), 3);
+        assert_eq!(scrollback.get(0).unwrap().into_owned(), first);
+        assert_eq!(texts(scrollback.tail(5)), vec!["red first", "line 1", "line 2", "line 3", "line 4"]);
+    }
+
+    fn push(scrollback: &mut Scrollback, shown: &mut ShownLines, text: &str) {
+        scrollback.push(StyledLine::plain(text));
+        shown.update(scrollback, scrollback.id(scrollback.len() - 1));
+    }
+
+    #[test]
+    fn test_filters_see_the_lines_in_memory() {
+        let scrollback = filled(4, true, 10);
+        let nine = Search::new("9$").unwrap().unwrap();
+        let shown = ShownLines::new(&scrollback, OutputView::default(), Some(&nine));
+        assert_eq!(shown.len(&scrollback), 10);
+        assert_eq!(shown.index(&scrollback, 2), Some(2));
+        assert_eq!(shown.matches(), &[9]);
+
+        let filter = LineFilter { search: Search::new("[13579]$").unwrap().unwrap(), invert: false };
+        let view = OutputView { streams: StreamFilter::All, filter: Some(&filter), json: None };
+        let shown = ShownLines::new(&scrollback, view, Some(&nine));
+        assert_eq!(shown.len(&scrollback), 2);
+        assert_eq!(shown.index(&scrollback, 1), Some(9));
+        assert_eq!(shown.position(&scrollback, 9), Some(1));
+        assert_eq!(shown.position(&scrollback, 8), None);
+        assert_eq!(shown.matches(), &[9]);
+    }
+
+    #[test]
+    fn test_shown_lines_follow_new_and_replaced_lines() {
+        let mut scrollback = filled(3, false, 3);
+        let filter = LineFilter { search: Search::new("keep").unwrap().unwrap(), invert: false };
+        let view = OutputView { streams: StreamFilter::All, filter: Some(&filter), json: None };
+        let search = Search::new("match").unwrap().unwrap();
+        let mut shown = ShownLines::new(&scrollback, view, Some(&search));
+        assert_eq!(shown.len(&scrollback), 0);
+
+        push(&mut scrollback, &mut shown, "keep, match");
+        push(&mut scrollback, &mut shown, "keep");
+        assert_eq!((shown.len(&scrollback), shown.matches()), (2, &VecDeque::from([3])));
+        assert_eq!(shown.index(&scrollback, 0), Some(1));
+
+        scrollback.replace(3, StyledLine::plain("gone")).unwrap();
+        shown.update(&scrollback, 3);
+        scrollback.replace(4, StyledLine::plain("keep, match")).unwrap();
+        shown.update(&scrollback, 4);
+        assert_eq!((shown.len(&scrollback), shown.matches()), (1, &VecDeque::from([4])));
+        assert!(shown.is_match(4));
+
+        // Lines that leave memory leave the view with them
+        for text in ["a", "b", "c"] {
+            push(&mut scrollback, &mut shown, text);
+        }
+        assert_eq!((shown.len(&scrollback), shown.matches()), (0, &VecDeque::new()));
+    }
+}
@@ -0,0 +1,190 @@
+use crate::ansi::{OutputStream, StyledLine};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::style::Style;
+use regex::{Regex, RegexBuilder};
+use std::collections::VecDeque;
+
+/// A regex searched for in the output buffer. Patterns without capitals
+/// match case-insensitively, as in `less -i` or `rg -S`.
+#[derive(Debug, Clone)]
+pub struct Search {
+    pub pattern: String,
+    regex: Regex,
+}
+
+impl Search {
+    /// Compiles `pattern`; an empty one means no search.
+    pub fn new(pattern: &str) -> Result<Option<Search>, regex::Error> {
+        if pattern.is_empty() {
+            return Ok(None);
+        }
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(!pattern.chars().any(char::is_uppercase))
+            .build()?;
+        Ok(Some(Search { pattern: pattern.to_string(), regex }))
+    }
+
+    pub fn is_match(&self, line: &StyledLine) -> bool {
+        self.regex.is_match(&line.text)
+    }
+
+    /// Marks every match in `line` with `style`.
+    pub fn highlight(&self, line: &mut StyledLine, style: Style) {
+        let ranges: Vec<_> = self.regex.find_iter(&line.text).map(|found| found.range()).collect();
+        for range in ranges {
+            line.highlight(range.start, range.end, style);
+        }
+    }
+}
+
+/// The `&` filter: only the lines matching the pattern are shown, or only
+/// the others when inverted. Messages from druns itself are always shown.
+#[derive(Debug, Clone)]
+pub struct LineFilter {
+    pub search: Search,
+    pub invert: bool,
+}
+
+impl LineFilter {
+    pub fn shows(&self, line: &StyledLine) -> bool {
+        line.stream == OutputStream::Druns || self.search.is_match(line) != self.invert
+    }
+}
+
+/// The match to jump to from `current` among `matches`, in ascending order:
+/// the next one down when `forward`, otherwise the next one up, wrapping
+/// around at either end. Without a current match, forward starts at the top
+/// and backward at the bottom.
+pub fn step(matches: &VecDeque<usize>, current: Option<usize>, forward: bool) -> Option<usize> {
+    let found = match (current, forward) {
+        (Some(current), true) => matches.get(matches.partition_point(|&index| index <= current)),
+        (Some(current), false) => matches.partition_point(|&index| index < current).checked_sub(1).and_then(|i| matches.get(i)),
+        (None, _) => None,
+    };
+    found.or(if forward { matches.front() } else { matches.back() }).copied()
+}
+
+/// What a key did to the prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PromptEvent {
+    Edited,
+    Submitted,
+    Cancelled,
+}
+
+/// What the prompt's input is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PromptKind {
+    #[default]
+    Search,
+    Filter,
+}
+
+impl PromptKind {
+    /// The key that opens the prompt, shown before the input.
+    pub fn symbol(self) -> char {
+        match self {
+            PromptKind::Search => '/',
+            PromptKind::Filter => '&',
+        }
+    }
+}
+
+/// A line of text typed at the bottom of the output pane.
+#[derive(Debug, Clone, Default)]
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub input: String,
+    /// The input doesn't compile as a regex
+    pub invalid: bool,
+}
+
+impl Prompt {
+    pub fn new(kind: PromptKind) -> Self {
+        Prompt { kind, ..Prompt::default() }
+    }
+
+    pub fn handle_key(&mut self, key: &KeyEvent) -> PromptEvent {
+        match key.code {
+            KeyCode::Enter => return PromptEvent::Submitted,
+            KeyCode::Esc => return PromptEvent::Cancelled,
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return PromptEvent::Cancelled,
+            KeyCode::Char('u') if key.modifiers.contains(KeyModifiers::CONTROL) => self.input.clear(),
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => self.input.push(c),
+            _ => {}
+        }
+        PromptEvent::Edited
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smart_case() {
+        let search = Search::new("error").unwrap().unwrap();
+        assert!(search.is_match(&StyledLine::plain("ERROR: boom")));
+        let search = Search::new("Error").unwrap().unwrap();
+        assert!(!search.is_match(&StyledLine::plain("ERROR: boom")));
+        assert!(Search::new("").unwrap().is_none());
+        assert!(Search::new("(").is_err());
+    }
+
+    #[test]
+    fn test_is_match() {
+        let search = Search::new(r"fail\w*").unwrap().unwrap();
+        assert!(search.is_match(&StyledLine::plain("test failed")));
+        assert!(!search.is_match(&StyledLine::plain("ok")));
+    }
+
+    #[test]
+    fn test_line_filter() {
+        let filter = LineFilter { search: Search::new("warn").unwrap().unwrap(), invert: false };
+        let separator = StyledLine::styled("── restart ──", Style::default());
+        assert!(filter.shows(&StyledLine::plain("WARN disk low")));
+        assert!(!filter.shows(&StyledLine::plain("all good")));
+        assert!(filter.shows(&separator));
+        let filter = LineFilter { invert: true, ..filter };
+        assert!(!filter.shows(&StyledLine::plain("WARN disk low")));
+        assert!(filter.shows(&StyledLine::plain("all good")));
+        assert!(filter.shows(&separator));
+    }
+
+    #[test]
+    fn test_step_wraps_around() {
+        let matches = VecDeque::from([2, 5, 9]);
+        assert_eq!(step(&matches, None, false), Some(9));
+        assert_eq!(step(&matches, None, true), Some(2));
+        assert_eq!(step(&matches, Some(5), true), Some(9));
+        assert_eq!(step(&matches, Some(9), true), Some(2));
+        assert_eq!(step(&matches, Some(5), false), Some(2));
+        assert_eq!(step(&matches, Some(2), false), Some(9));
+        assert_eq!(step(&VecDeque::new(), Some(2), false), None);
+    }
+
+    #[test]
+    fn test_highlight_marks_every_match() {
+        let search = Search::new("o").unwrap().unwrap();
+        let mut line = StyledLine::plain("foo bar");
+        let style = Style::default().add_modifier(ratatui::style::Modifier::REVERSED);
+        search.highlight(&mut line, style);
+        assert_eq!(line.runs.len(), 1);
+        assert_eq!((line.runs[0].start, line.runs[0].end), (1, 3));
+    }
+
+    #[test]
+    fn test_prompt_editing() {
+        let mut prompt = Prompt::default();
+        let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+        assert_eq!(prompt.handle_key(&key(KeyCode::Char('a'))), PromptEvent::Edited);
+        prompt.handle_key(&key(KeyCode::Char('b')));
+        prompt.handle_key(&key(KeyCode::Backspace));
+        assert_eq!(prompt.input, "a");
+        assert_eq!(prompt.handle_key(&key(KeyCode::Enter)), PromptEvent::Submitted);
+        assert_eq!(prompt.handle_key(&key(KeyCode::Esc)), PromptEvent::Cancelled);
+    }
+}
@@ -0,0 +1,66 @@
+use std::future::poll_fn;
+use std::task::Poll;
+use tokio::signal::unix::{signal, Signal, SignalKind};
+
+/// Signals sent to druns that are passed on to the child's process group.
+pub const FORWARDED_SIGNALS: [i32; 6] = [
+    libc::SIGINT,
+    libc::SIGTERM,
+    libc::SIGHUP,
+    libc::SIGQUIT,
+    libc::SIGTSTP,
+    libc::SIGCONT,
+];
+
+/// Catches the signals in [`FORWARDED_SIGNALS`] so druns can hand them to the
+/// child instead of acting on them itself.
+pub struct ForwardedSignals {
+    streams: Vec<(i32, Signal)>,
+}
+
+impl ForwardedSignals {
+    pub fn new() -> std::io::Result<Self> {
+        let streams = FORWARDED_SIGNALS
+            .iter()
+            .map(|&number| Ok((number, signal(SignalKind::from_raw(number))?)))
+            .collect::<std::io::Result<_>>()?;
+        Ok(ForwardedSignals { streams })
+    }
+
+    /// Waits for the next signal and returns its number.
+    pub async fn recv(&mut self) -> i32 {
+        poll_fn(|cx| {
+            for (number, stream) in &mut self.streams {
+                if stream.poll_recv(cx).is_ready() {
+                    return Poll::Ready(*number);
+                }
+            }
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+/// Whether a forwarded signal asks the child to go away, so druns should
+/// escalate to SIGKILL if it is still running after the grace period.
+pub fn is_termination(signal: i32) -> bool {
+    matches!(signal, libc::SIGINT | libc::SIGTERM | libc::SIGHUP)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_signals_are_caught() {
+        let mut signals = ForwardedSignals::new().unwrap();
+        unsafe { libc::raise(libc::SIGHUP) };
+        assert_eq!(signals.recv().await, libc::SIGHUP);
+    }
+
+    #[test]
+    fn test_is_termination() {
+        assert!(is_termination(libc::SIGTERM));
+        assert!(!is_termination(libc::SIGTSTP));
+    }
+}
@@ -0,0 +1,916 @@
+use crate::ansi::{OutputStream, StyledLine};
+use crate::disk::monitor_disk;
+use crate::keys::encode_key;
+#[cfg(feature = "experimental-pcap")]
+use crate::network::monitor_network;
+use crate::network::{monitor_sockets, NetworkSample};
+use crate::output_log::OutputLogs;
+use crate::process::{ProcessManager, SpawnOptions};
+use crate::process_tree::{signal_descendant, ProcessRow, TreeUsage};
+use crate::restart::{RestartDecision, RestartPolicy, RestartTracker};
+use crate::scrollback::{Scrollback, ShownLines};
+use crate::search::Search;
+use crate::signals::is_termination;
+use crate::ui_output::{DedupMode, OutputView};
+use crate::utils::{format_duration, format_exit_status};
+use crossterm::event::KeyEvent;
+use ratatui::style::Style;
+use std::process::ExitStatus;
+use sysinfo::System;
+use tokio::sync::mpsc;
+use tokio::time::{Duration, Instant};
+
+/// How long network figures are kept without a new report before they're
+/// dropped. Packet captures come in every couple of seconds, not every one.
+const NETWORK_STALE_AFTER: Duration = Duration::from_secs(3);
+
+/// How druns looks after each process, beyond how it is spawned.
+#[derive(Debug, Clone)]
+pub struct SupervisionOptions {
+    pub max_output_lines: usize,
+    /// Page output past `max_output_lines` out to disk instead of dropping it
+    pub unlimited_scrollback: bool,
+    /// Style of the lines druns adds itself, such as restart separators
+    pub separator_style: Style,
+    pub restart_tracker: RestartTracker,
+    /// Stop a run that takes longer than this
+    pub timeout: Option<Duration>,
+    /// Which repeated lines are collapsed into one
+    pub dedup: DedupMode,
+    /// Interface the experimental-pcap monitor captures on
+    pub network_device: String,
+}
+
+/// A restart waiting for what the previous run left in its process group to
+/// exit, checked every tick so that druns keeps drawing meanwhile.
+struct PendingRespawn {
+    status: ExitStatus,
+    cause: String,
+    requested: Instant,
+    /// Leftovers still running by then are SIGKILLed
+    kill_at: Instant,
+}
+
+/// One command under druns' control: the running child plus its output,
+/// resource history and stop/restart state. Single-command mode has one of
+/// these, Procfile mode one per entry.
+pub struct SupervisedProcess {
+    pub name: String,
+    pub command: Vec<String>,
+    spawn_options: SpawnOptions,
+    pub process_manager: ProcessManager,
+    logs: OutputLogs,
+    pub output: Scrollback,
+    /// Which lines of `output` the pane shows, and the search matches
+    pub shown: ShownLines,
+    /// Ids of the progress lines still being redrawn, at most one per stream
+    open_partials: Vec<(OutputStream, usize)>,
+    dedup: DedupMode,
+    output_rx: mpsc::UnboundedReceiver<StyledLine>,
+    separator_style: Style,
+    #[allow(dead_code)]
+    network_stats_rx: Option<StatsReceiver>,
+    network_device: String,
+    socket_stats_rx: mpsc::UnboundedReceiver<NetworkSample>,
+    disk_stats_rx: StatsReceiver,
+    /// When the first run started; `start_time` is the current run's
+    pub first_start: Instant,
+    /// Wall-clock time of `first_start`, for the elapsed-time gutter
+    pub first_started_at: chrono::DateTime<chrono::Local>,
+    pub start_time: Instant,
+    pub last_run: chrono::DateTime<chrono::Local>,
+    // History for averaging
+    pub cpu_history: Vec<f32>,
+    pub memory_history: Vec<u64>,
+    pub disk_read_history: Vec<u64>,
+    pub disk_write_history: Vec<u64>,
+    /// Bytes received and sent each second, zero while they aren't measured
+    pub network_rx_history: Vec<u64>,
+    pub network_tx_history: Vec<u64>,
+    pub connection_history: Vec<u64>,
+    // Display values (averaged)
+    pub display_cpu: f32,
+    pub display_memory: u64,
+    /// Processes and threads in the tree, as of the last sample
+    pub process_count: usize,
+    pub thread_count: usize,
+    /// Every process in the tree, as of the last sample
+    pub tree: Vec<ProcessRow>,
+    pub display_disk_read: u64,
+    pub display_disk_write: u64,
+    pub network_rx: u64,
+    pub network_tx: u64,
+    /// `network_rx` and `network_tx` are measured, not just zero
+    pub network_measured: bool,
+    /// When `network_rx` and `network_tx` were last reported
+    network_reported_at: Option<Instant>,
+    /// Open sockets in the tree, as of the last sample
+    pub network_connections: usize,
+    pub network_listening: usize,
+    iteration_count: u32,
+    // Output scrolling state
+    pub follow_mode: bool,
+    pub scroll_offset: usize,
+    pane_size: (u16, u16),
+    /// When a stop is under way, the point at which it escalates to SIGKILL
+    pub stop_deadline: Option<Instant>,
+    /// Spawn the command again once the current run exits
+    pub pending_restart: bool,
+    /// The run has ended and the command is spawned again once its leftovers are gone
+    respawn: Option<PendingRespawn>,
+    /// Why the pending restart was requested, for the separator line
+    restart_cause: String,
+    pub restart_count: u32,
+    pub last_exit: Option<ExitStatus>,
+    /// The status of a run that finished and was not restarted
+    pub exited: Option<ExitStatus>,
+    /// The run finished in watch mode and the next change reruns it
+    pub waiting_for_changes: bool,
+    // Automatic restarts
+    restart_tracker: RestartTracker,
+    /// An automatic restart is due at this point
+    pub restart_at: Option<Instant>,
+    /// Why automatic restarts stopped, once they have
+    pub gave_up: Option<String>,
+    timeout: Option<Duration>,
+    /// The current run was stopped for taking longer than the timeout
+    pub timed_out: bool,
+}
+
+impl SupervisedProcess {
+    pub fn spawn(
+        name: String,
+        command: Vec<String>,
+        spawn_options: SpawnOptions,
+        logs: OutputLogs,
+        options: SupervisionOptions,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut process_manager = ProcessManager::new(&command, &spawn_options)?;
+        let output_rx = process_manager.start_output_reading(logs.clone());
+        let (network_stats_rx, socket_stats_rx, disk_stats_rx) = spawn_monitors(process_manager.pid, &options.network_device);
+        let pane_size = (spawn_options.cols, spawn_options.rows);
+
+        Ok(SupervisedProcess {
+            name,
+            command,
+            spawn_options,
+            process_manager,
+            logs,
+            output: Scrollback::new(options.max_output_lines, options.unlimited_scrollback),
+            shown: ShownLines::default(),
+            open_partials: Vec::new(),
+            dedup: options.dedup,
+            output_rx,
+            separator_style: options.separator_style,
+            network_stats_rx,
+            network_device: options.network_device,
+            socket_stats_rx,
+            disk_stats_rx,
+            first_start: Instant::now(),
+            first_started_at: chrono::Local::now(),
+            start_time: Instant::now(),
+            last_run: chrono::Local::now(),
+            cpu_history: Vec::new(),
+            memory_history: Vec::new(),
+            disk_read_history: Vec::new(),
+            disk_write_history: Vec::new(),
+            network_rx_history: Vec::new(),
+            network_tx_history: Vec::new(),
+            connection_history: Vec::new(),
+            display_cpu: 0.0,
+            display_memory: 0,
+            process_count: 0,
+            thread_count: 0,
+            tree: Vec::new(),
+            display_disk_read: 0,
+            display_disk_write: 0,
+            network_rx: 0,
+            network_tx: 0,
+            network_measured: false,
+            network_reported_at: None,
+            network_connections: 0,
+            network_listening: 0,
+            iteration_count: 0,
+            follow_mode: true, // Start in follow mode
+            scroll_offset: 0,
+            pane_size,
+            stop_deadline: None,
+            pending_restart: false,
+            respawn: None,
+            restart_cause: String::new(),
+            restart_count: 0,
+            last_exit: None,
+            exited: None,
+            waiting_for_changes: false,
+            restart_tracker: options.restart_tracker,
+            restart_at: None,
+            gave_up: None,
+            timeout: options.timeout,
+            timed_out: false,
+        })
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.exited.is_none()
+    }
+
+    /// Moves everything the output readers have produced into the buffer.
+    pub fn drain_output(&mut self) {
+        while let Ok(line) = self.output_rx.try_recv() {
+            self.push_output_line(line);
+        }
+    }
+
+    /// Returns the status of a run that has just ended, once.
+    pub fn check_exit(&mut self) -> Option<ExitStatus> {
+        if self.exited.is_some() || self.respawn.is_some() {
+            return None;
+        }
+        self.process_manager.try_wait().ok().flatten()
+    }
+
+    /// Marks a run as finished without a restart. With `waiting_for_changes`
+    /// the next change reruns it; `announce` adds a separator saying how it ended.
+    pub fn finish(&mut self, status: ExitStatus, waiting_for_changes: bool, announce: bool) {
+        self.drain_output();
+        self.exited = Some(status);
+        self.last_exit = Some(status);
+        self.waiting_for_changes = waiting_for_changes;
+        self.stop_deadline = None;
+        if announce {
+            let message = if waiting_for_changes {
+                format!("── exited with {}, waiting for changes ──", format_exit_status(&status))
+            } else {
+                format!("── exited with {} ──", format_exit_status(&status))
+            };
+            self.push_separator(message);
+        }
+    }
+
+    /// Applies the restart policy to a run that ended on its own. Returns
+    /// true if a restart was scheduled; otherwise the caller finishes the run.
+    pub fn schedule_restart(&mut self, status: ExitStatus) -> bool {
+        let uptime = self.start_time.elapsed();
+        match self.restart_tracker.decide(&status, uptime, Instant::now().into_std()) {
+            RestartDecision::Restart(delay) => {
+                self.finish(status, false, false);
+                self.restart_at = Some(Instant::now() + delay);
+                let message = format!(
+                    "── exited with {}, restarting in {:.1}s ──",
+                    format_exit_status(&status),
+                    delay.as_secs_f64()
+                );
+                self.push_separator(message);
+                true
+            }
+            RestartDecision::GiveUp(reason) => {
+                self.push_separator(format!("── not restarting: {reason} ──"));
+                self.gave_up = Some(reason);
+                false
+            }
+            RestartDecision::Stay => false,
+        }
+    }
+
+    /// Stops the run once it has taken longer than the timeout.
+    pub async fn check_timeout(&mut self, grace_period: Duration) {
+        let Some(timeout) = self.timeout else { return };
+        if !self.is_running() || self.timed_out || self.start_time.elapsed() < timeout {
+            return;
+        }
+        self.timed_out = true;
+        self.pending_restart = false;
+        self.push_separator(format!("── timed out after {}, stopping ──", format_duration(timeout)));
+        if self.stop_deadline.is_none() {
+            self.request_stop(grace_period).await;
+        }
+    }
+
+    /// Time left before the timeout stops the current run: zero once it has,
+    /// `None` without a timeout or after the run ended on its own.
+    pub fn timeout_left(&self) -> Option<Duration> {
+        let timeout = self.timeout?;
+        if self.timed_out {
+            return Some(Duration::ZERO);
+        }
+        if !self.is_running() {
+            return None;
+        }
+        Some(timeout.saturating_sub(self.start_time.elapsed()))
+    }
+
+    /// Runs a scheduled automatic restart once its backoff has passed.
+    pub fn check_restart_timer(&mut self, grace_period: Duration) {
+        if self.restart_at.is_some_and(|at| Instant::now() >= at) {
+            self.restart_at = None;
+            if let Some(status) = self.exited {
+                self.restart(status, "automatic restart", grace_period);
+            }
+        }
+    }
+
+    /// The restart policy and what it is doing, for the sidebar; `None` without a policy.
+    pub fn restart_status(&self) -> Option<String> {
+        let policy = self.restart_tracker.policy();
+        if policy == RestartPolicy::Never {
+            return None;
+        }
+        if let Some(at) = self.restart_at {
+            let remaining = at.saturating_duration_since(Instant::now());
+            return Some(format!("in {:.0}s", remaining.as_secs_f64().ceil()));
+        }
+        if self.gave_up.is_some() {
+            return Some("gave up".to_string());
+        }
+        Some(policy.name().to_string())
+    }
+
+    /// Starts the graceful stop (SIGTERM, then SIGKILL after the grace
+    /// period), or force-kills if a stop is already under way.
+    pub async fn request_stop(&mut self, grace_period: Duration) {
+        if !self.is_running() {
+            return;
+        }
+        if self.stop_deadline.is_some() {
+            let _ = self.process_manager.kill().await;
+        } else {
+            let _ = self.process_manager.terminate(libc::SIGTERM);
+            self.stop_deadline = Some(Instant::now() + grace_period);
+        }
+    }
+
+    /// SIGKILLs the child once its grace period has run out.
+    pub async fn check_stop_deadline(&mut self) {
+        if self.stop_deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            let _ = self.process_manager.kill().await;
+        }
+    }
+
+    /// Passes a signal druns received on to the child's process group.
+    pub async fn forward_signal(&mut self, signal: i32, grace_period: Duration) {
+        if !self.is_running() {
+            return;
+        }
+        if !is_termination(signal) {
+            let _ = self.process_manager.signal(signal);
+            return;
+        }
+        if signal == libc::SIGINT && self.stop_deadline.is_some() {
+            // A second interrupt means stop now, same as a second ctrl-c
+            let _ = self.process_manager.kill().await;
+            return;
+        }
+        self.pending_restart = false;
+        let _ = self.process_manager.terminate(signal);
+        if self.stop_deadline.is_none() {
+            self.stop_deadline = Some(Instant::now() + grace_period);
+        }
+    }
+
+    /// Reruns the command, stopping the current run first if it is still going.
+    /// With `force`, asking again while a stop is under way kills the child.
+    /// Returns false if the command could not be started again.
+    pub async fn request_restart(&mut self, cause: String, force: bool, grace_period: Duration) -> bool {
+        // Asking for a run is a fresh start as far as backoff and crash loops go
+        self.restart_tracker.reset();
+        if let Some(pending) = &mut self.respawn {
+            // Asking again while the leftovers are stopping kills them
+            if force {
+                pending.kill_at = Instant::now();
+            }
+            return self.check_respawn();
+        }
+        if let Some(status) = self.exited {
+            return self.restart(status, &cause, grace_period);
+        }
+        if self.stop_deadline.is_some() && !force {
+            return true;
+        }
+        self.restart_cause = cause;
+        self.pending_restart = true;
+        self.request_stop(grace_period).await;
+        true
+    }
+
+    /// Runs the restart requested before the run ended with `status`.
+    pub fn complete_restart(&mut self, status: ExitStatus, grace_period: Duration) -> bool {
+        let cause = std::mem::take(&mut self.restart_cause);
+        self.restart(status, &cause, grace_period)
+    }
+
+    /// Whether a restart is waiting for the previous run's leftovers.
+    pub fn is_respawning(&self) -> bool {
+        self.respawn.is_some()
+    }
+
+    /// Drops a restart that is waiting for leftovers, leaving the process exited.
+    pub fn cancel_respawn(&mut self) {
+        self.respawn = None;
+    }
+
+    /// Starts running the command again after the previous run exited with
+    /// `status`. Anything that run left in its process group gets SIGTERM and
+    /// the grace period to exit first; `check_respawn` spawns the command once
+    /// it has. Returns false if the command could not be started again.
+    fn restart(&mut self, status: ExitStatus, cause: &str, grace_period: Duration) -> bool {
+        let _ = self.process_manager.terminate(libc::SIGTERM);
+        let now = Instant::now();
+        self.respawn = Some(PendingRespawn { status, cause: cause.to_string(), requested: now, kill_at: now + grace_period });
+        self.exited = Some(status);
+        self.stop_deadline = None;
+        self.pending_restart = true;
+        self.check_respawn()
+    }
+
+    /// Spawns a pending restart once the previous run's process group is
+    /// empty and its output has been read, SIGKILLing leftovers that outlast
+    /// the grace period. On failure the error goes into the output, the
+    /// process is left as exited and false is returned.
+    pub fn check_respawn(&mut self) -> bool {
+        let Some((requested, kill_at)) = self.respawn.as_ref().map(|pending| (pending.requested, pending.kill_at)) else {
+            return true;
+        };
+        let now = Instant::now();
+        // Signal 0 only checks whether anything is left in the group
+        if self.process_manager.pid != 0 && self.process_manager.signal(0).is_ok() {
+            if now >= kill_at {
+                let _ = self.process_manager.signal(libc::SIGKILL);
+            }
+            return true;
+        }
+
+        // Collect what the previous run printed last before its readers are
+        // dropped, giving them a moment to finish
+        let readers_done = loop {
+            match self.output_rx.try_recv() {
+                Ok(line) => self.push_output_line(line),
+                Err(mpsc::error::TryRecvError::Empty) => break false,
+                Err(mpsc::error::TryRecvError::Disconnected) => break true,
+            }
+        };
+        if !readers_done && now < requested + Duration::from_millis(200) {
+            return true;
+        }
+
+        let Some(PendingRespawn { status, cause, .. }) = self.respawn.take() else { return true };
+        if let Err(e) = self.respawn(status, &cause) {
+            let mut line = StyledLine::plain(format!("druns: restart failed: {e}"));
+            line.stream = OutputStream::Druns;
+            self.push_output_line(line);
+            self.exited = Some(status);
+            self.waiting_for_changes = false;
+            self.pending_restart = false;
+            self.stop_deadline = None;
+            return false;
+        }
+        true
+    }
+
+    fn respawn(&mut self, status: ExitStatus, cause: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.restart_count += 1;
+        self.last_exit = Some(status);
+        let separator = format!(
+            "── restart #{}: {cause} (previous run exited with {}) ──",
+            self.restart_count,
+            format_exit_status(&status)
+        );
+        self.push_separator(separator);
+
+        self.spawn_options.cols = self.pane_size.0;
+        self.spawn_options.rows = self.pane_size.1;
+        let mut process_manager = ProcessManager::new(&self.command, &self.spawn_options)?;
+        self.output_rx = process_manager.start_output_reading(self.logs.clone());
+        (self.network_stats_rx, self.socket_stats_rx, self.disk_stats_rx) =
+            spawn_monitors(process_manager.pid, &self.network_device);
+        self.process_manager = process_manager;
+
+        self.start_time = Instant::now();
+        self.last_run = chrono::Local::now();
+        self.cpu_history.clear();
+        self.memory_history.clear();
+        self.disk_read_history.clear();
+        self.disk_write_history.clear();
+        self.network_rx_history.clear();
+        self.network_tx_history.clear();
+        self.connection_history.clear();
+        self.display_cpu = 0.0;
+        self.display_memory = 0;
+        self.display_disk_read = 0;
+        self.display_disk_write = 0;
+        self.network_rx = 0;
+        self.network_tx = 0;
+        self.network_measured = false;
+        self.network_reported_at = None;
+        self.iteration_count = 0;
+        self.stop_deadline = None;
+        self.pending_restart = false;
+        self.exited = None;
+        self.waiting_for_changes = false;
+        self.restart_at = None;
+        self.gave_up = None;
+        self.timed_out = false;
+        Ok(())
+    }
+
+    /// Sends `signal` to one process of the tree, leaving the rest of it
+    /// running, and notes it in the output.
+    pub fn signal_descendant(&mut self, pid: u32, signal: i32, name: &str) {
+        let message = match signal_descendant(self.process_manager.pid, pid, signal) {
+            Ok(()) => format!("── sent {name} to {pid} ──"),
+            Err(e) => format!("── could not send {name} to {pid}: {e} ──"),
+        };
+        self.push_separator(message);
+    }
+
+    /// Adds a line from druns itself to the output and the log.
+    pub fn push_separator(&mut self, text: String) {
+        let mut line = StyledLine::styled(&text, self.separator_style);
+        let arrived = chrono::Local::now();
+        line.arrived = Some(arrived);
+        self.logs.write_line(arrived, OutputStream::Druns, &text, &text);
+        self.push_output_line(line);
+    }
+
+    pub fn push_output_line(&mut self, mut line: StyledLine) {
+        line.arrived.get_or_insert_with(chrono::Local::now);
+        let (stream, partial) = (line.stream, line.partial);
+        // A progress bar redraw, or the line it ends as, replaces the stream's
+        // previous redraw, even with lines from the other stream in between
+        let open = self.open_partials.iter().position(|&(open, _)| open == stream);
+        let mut line = match open.map(|position| self.open_partials.swap_remove(position).1) {
+            Some(id) => match self.output.replace(id, line) {
+                Ok(()) => {
+                    self.shown.update(&self.output, id);
+                    if partial {
+                        self.open_partials.push((stream, id));
+                    }
+                    return;
+                }
+                // The redraw has left memory since, so this one goes at the end
+                Err(line) => line,
+            },
+            None => line,
+        };
+        match self.output.last_mut() {
+            // The latest of the repeats is shown, with the count
+            Some(last) if self.dedup.repeats(last, &line) => {
+                line.repeats = last.repeats + 1;
+                *last = line;
+            }
+            _ => self.output.push(line),
+        }
+        let id = self.output.id(self.output.len() - 1);
+        self.shown.update(&self.output, id);
+        if partial {
+            self.open_partials.push((stream, id));
+        }
+    }
+
+    /// Works out the shown lines and search matches again for a new view or search.
+    pub fn set_view(&mut self, view: OutputView, search: Option<&Search>) {
+        self.shown = ShownLines::new(&self.output, view, search);
+    }
+
+    /// Keeps the child's terminal the same size as its output pane.
+    pub fn resize(&mut self, pane_size: (u16, u16)) {
+        if pane_size != self.pane_size {
+            self.pane_size = pane_size;
+            let _ = self.process_manager.resize(pane_size.0, pane_size.1);
+        }
+    }
+
+    pub fn send_key(&mut self, key: &KeyEvent) {
+        let application_cursor = self
+            .process_manager
+            .screen()
+            .is_some_and(|screen| screen.lock().screen().application_cursor());
+        if let Some(bytes) = encode_key(key, application_cursor) {
+            let _ = self.process_manager.write_input(&bytes);
+            // Typing jumps back to the live output, like a terminal does
+            self.follow_mode = true;
+            self.scroll_offset = 0;
+        }
+    }
+
+    /// Takes one sample of the child's resource usage. Called every tick
+    /// (20ms), it updates the history and display values once a second.
+    pub fn sample(&mut self, system: &System) {
+        // Calculate stats over the whole process tree
+        self.tree = ProcessRow::list(system, self.process_manager.pid);
+        let usage = TreeUsage::of(&self.tree);
+        let (cpu_percent, memory_used) = (usage.cpu_percent, usage.memory);
+        self.process_count = usage.processes;
+        self.thread_count = usage.threads;
+
+        // Get latest network stats: sockets from /proc, and bytes when the
+        // tree has a network namespace of its own or packets are captured
+        if let Ok(sample) = self.socket_stats_rx.try_recv() {
+            self.network_connections = sample.connections;
+            self.network_listening = sample.listening;
+            if let Some(throughput) = sample.throughput {
+                (self.network_rx, self.network_tx) = throughput;
+                self.network_measured = true;
+                self.network_reported_at = Some(Instant::now());
+            }
+        }
+        #[cfg(feature = "experimental-pcap")]
+        if let Ok((rx, tx)) = self.network_stats_rx.as_mut().unwrap().try_recv() {
+            (self.network_rx, self.network_tx) = (rx, tx);
+            self.network_measured = true;
+            self.network_reported_at = Some(Instant::now());
+        }
+        // Like disk, traffic no source reports lately is none, not the last figure
+        if self.network_reported_at.is_none_or(|at| at.elapsed() > NETWORK_STALE_AFTER) {
+            (self.network_rx, self.network_tx) = (0, 0);
+            self.network_measured = false;
+        }
+
+        // Get latest disk stats
+        let mut disk_read = 0u64;
+        let mut disk_write = 0u64;
+        if let Ok((r, w)) = self.disk_stats_rx.try_recv() {
+            disk_read = r;
+            disk_write = w;
+        }
+
+        // Push to history every second (50 iterations)
+        if self.iteration_count.is_multiple_of(50) {
+            self.cpu_history.push(cpu_percent);
+            self.memory_history.push(memory_used);
+            self.disk_read_history.push(disk_read);
+            self.disk_write_history.push(disk_write);
+            self.network_rx_history.push(self.network_rx);
+            self.network_tx_history.push(self.network_tx);
+            self.connection_history.push(self.network_connections as u64);
+
+            // Limit history to last 5 minutes (300 samples at 1s intervals)
+            const MAX_HISTORY: usize = 300;
+            if self.cpu_history.len() > MAX_HISTORY {
+                self.cpu_history.remove(0);
+                self.memory_history.remove(0);
+                self.disk_read_history.remove(0);
+                self.disk_write_history.remove(0);
+                self.network_rx_history.remove(0);
+                self.network_tx_history.remove(0);
+                self.connection_history.remove(0);
+            }
+        }
+
+        // Update display values every second (50 iterations)
+        self.iteration_count += 1;
+        if self.iteration_count.is_multiple_of(50) {
+            self.display_cpu = if !self.cpu_history.is_empty() {
+                self.cpu_history.iter().sum::<f32>() / self.cpu_history.len() as f32
+            } else {
+                0.0
+            };
+            self.display_memory = average(&self.memory_history);
+            self.display_disk_read = average(&self.disk_read_history);
+            self.display_disk_write = average(&self.disk_write_history);
+        }
+    }
+}
+
+fn average(history: &[u64]) -> u64 {
+    if history.is_empty() {
+        return 0;
+    }
+    (history.iter().sum::<u64>() as f64 / history.len() as f64).round() as u64
+}
+
+type StatsReceiver = mpsc::UnboundedReceiver<(u64, u64)>;
+
+/// Starts the packet capture (with experimental-pcap), socket and disk
+/// monitors for a child. Each monitor stops once its receiver is dropped.
+fn spawn_monitors(pid: u32, network_device: &str) -> (Option<StatsReceiver>, mpsc::UnboundedReceiver<NetworkSample>, StatsReceiver) {
+    #[cfg(feature = "experimental-pcap")]
+    let network_stats_rx = {
+        let (tx, rx) = mpsc::unbounded_channel::<(u64, u64)>();
+        let device = network_device.to_string();
+        tokio::spawn(async move {
+            monitor_network(pid, device, tx).await;
+        });
+        Some(rx)
+    };
+    #[cfg(not(feature = "experimental-pcap"))]
+    let network_stats_rx = {
+        let _ = network_device;
+        None
+    };
+
+    let (socket_stats_tx, socket_stats_rx) = mpsc::unbounded_channel::<NetworkSample>();
+    tokio::spawn(async move {
+        monitor_sockets(pid, socket_stats_tx).await;
+    });
+
+    let (disk_stats_tx, disk_stats_rx) = mpsc::unbounded_channel::<(u64, u64)>();
+    tokio::spawn(async move {
+        monitor_disk(pid, disk_stats_tx).await;
+    });
+
+    (network_stats_rx, socket_stats_rx, disk_stats_rx)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::OutputMode;
+    use crate::restart::RestartLimits;
+    use std::os::unix::process::ExitStatusExt;
+    use tokio::time::timeout;
+
+    fn spawn_with_policy(script: &str, policy: RestartPolicy) -> SupervisedProcess {
+        let options = SpawnOptions { mode: OutputMode::Pipes, shell: Some("sh".to_string()), ..Default::default() };
+        let limits = RestartLimits {
+            max_restarts: Some(1),
+            backoff: Duration::from_millis(10),
+            backoff_max: Duration::from_millis(10),
+            crash_loop_restarts: 0,
+            crash_loop_window: Duration::from_secs(60),
+        };
+        let supervision = SupervisionOptions {
+            max_output_lines: 100,
+            unlimited_scrollback: false,
+            separator_style: Style::default(),
+            restart_tracker: RestartTracker::new(policy, limits),
+            timeout: Some(Duration::from_millis(200)),
+            dedup: DedupMode::Off,
+            network_device: "any".to_string(),
+        };
+        SupervisedProcess::spawn("test".to_string(), vec![script.to_string()], options, OutputLogs::default(), supervision).unwrap()
+    }
+
+    fn spawn(script: &str) -> SupervisedProcess {
+        spawn_with_policy(script, RestartPolicy::Never)
+    }
+
+    async fn wait_for_exit(process: &mut SupervisedProcess) -> ExitStatus {
+        timeout(Duration::from_secs(5), async {
+            loop {
+                if let Some(status) = process.check_exit() {
+                    return status;
+                }
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap()
+    }
+
+    /// Ticks a pending restart along until the command runs again.
+    async fn wait_for_respawn(process: &mut SupervisedProcess) {
+        timeout(Duration::from_secs(5), async {
+            while process.is_respawning() {
+                assert!(process.check_respawn());
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap()
+    }
+
+    #[test]
+    fn test_average() {
+        assert_eq!(average(&[]), 0);
+        assert_eq!(average(&[100, 200, 300]), 200);
+        assert_eq!(average(&[1, 2]), 2);
+    }
+
+    #[tokio::test]
+    async fn test_finish_announces_the_exit() {
+        let mut process = spawn("echo hello; exit 3");
+        let status = wait_for_exit(&mut process).await;
+        process.finish(status, false, true);
+        assert!(!process.is_running());
+        assert!(process.check_exit().is_none());
+        let texts: Vec<&str> = process.output.in_memory().map(|line| line.text.as_str()).collect();
+        assert_eq!(texts.last(), Some(&"── exited with code 3 ──"));
+    }
+
+    #[tokio::test]
+    async fn test_progress_bar_ends_as_one_line() {
+        let mut process = spawn("printf 'get 10%%\\r'; sleep 0.2; printf 'get 100%%\\ndone\\n'");
+        let status = wait_for_exit(&mut process).await;
+        // Give the reader a moment to catch up with the exit
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        process.finish(status, false, false);
+        let texts: Vec<&str> = process.output.in_memory().map(|line| line.text.as_str()).collect();
+        assert_eq!(texts, vec!["get 100%", "done"]);
+    }
+
+    #[tokio::test]
+    async fn test_progress_bar_is_redrawn_past_lines_from_the_other_stream() {
+        let mut process = spawn("true");
+        let line = |text: &str, stream, partial| StyledLine { stream, partial, ..StyledLine::plain(text) };
+        process.push_output_line(line("get 10%", OutputStream::Stdout, true));
+        process.push_output_line(line("slow mirror", OutputStream::Stderr, false));
+        process.push_output_line(line("get 50%", OutputStream::Stdout, true));
+        process.push_output_line(line("get 100%", OutputStream::Stdout, false));
+        process.push_output_line(line("done", OutputStream::Stdout, false));
+        let texts: Vec<&str> = process.output.in_memory().map(|line| line.text.as_str()).collect();
+        assert_eq!(texts, vec!["get 100%", "slow mirror", "done"]);
+    }
+
+    #[tokio::test]
+    async fn test_repeated_lines_are_counted() {
+        let mut process = spawn("printf 'ping 1\\nping 2\\nping 3\\npong\\n'; sleep 0.2");
+        process.dedup = DedupMode::Numbers;
+        let status = wait_for_exit(&mut process).await;
+        process.finish(status, false, false);
+        let lines: Vec<(&str, usize)> = process.output.in_memory().map(|line| (line.text.as_str(), line.repeats)).collect();
+        assert_eq!(lines, vec![("ping 3", 2), ("pong", 0)]);
+    }
+
+    #[tokio::test]
+    async fn test_restart_after_exit_runs_the_command_again() {
+        let mut process = spawn("echo run");
+        let status = wait_for_exit(&mut process).await;
+        process.finish(status, true, false);
+        assert!(process.request_restart("test".to_string(), false, Duration::from_secs(1)).await);
+        wait_for_respawn(&mut process).await;
+        assert!(process.is_running());
+        assert_eq!(process.restart_count, 1);
+        assert!(!process.waiting_for_changes);
+        wait_for_exit(&mut process).await;
+    }
+
+    #[tokio::test]
+    async fn test_network_figures_no_source_reports_are_dropped() {
+        let mut process = spawn("echo run");
+        (process.network_rx, process.network_tx, process.network_measured) = (100, 200, true);
+        process.network_reported_at = Some(Instant::now());
+        process.sample(&System::new());
+        assert_eq!((process.network_rx, process.network_tx), (100, 200));
+
+        process.network_reported_at = Instant::now().checked_sub(NETWORK_STALE_AFTER * 2);
+        process.sample(&System::new());
+        assert_eq!((process.network_rx, process.network_tx, process.network_measured), (0, 0, false));
+        wait_for_exit(&mut process).await;
+    }
+
+    #[tokio::test]
+    async fn test_restart_waits_for_leftovers_without_blocking() {
+        // The background job ignores SIGTERM, so only the SIGKILL after the grace period stops it
+        let mut process = spawn("trap '' TERM; (trap '' TERM; sleep 30) & echo started");
+        let status = wait_for_exit(&mut process).await;
+        process.finish(status, true, false);
+        let requested = Instant::now();
+        assert!(process.request_restart("test".to_string(), false, Duration::from_millis(300)).await);
+        assert!(requested.elapsed() < Duration::from_millis(100));
+        assert!(process.is_respawning());
+        assert!(process.pending_restart);
+        assert_eq!(process.check_exit(), None);
+
+        wait_for_respawn(&mut process).await;
+        assert!(requested.elapsed() >= Duration::from_millis(300));
+        assert!(process.is_running());
+        assert_eq!(process.restart_count, 1);
+        wait_for_exit(&mut process).await;
+        let _ = process.process_manager.signal(libc::SIGKILL);
+    }
+
+    #[tokio::test]
+    async fn test_failing_command_is_restarted_until_the_limit() {
+        let mut process = spawn_with_policy("exit 1", RestartPolicy::OnFailure);
+        let status = wait_for_exit(&mut process).await;
+        assert!(process.schedule_restart(status));
+        assert!(process.restart_at.is_some());
+        assert!(!process.is_running());
+
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        process.check_restart_timer(Duration::from_secs(1));
+        wait_for_respawn(&mut process).await;
+        assert!(process.is_running());
+        assert_eq!(process.restart_count, 1);
+
+        // max_restarts is 1, so the second failure stays down
+        let status = wait_for_exit(&mut process).await;
+        assert!(!process.schedule_restart(status));
+        assert_eq!(process.gave_up.as_deref(), Some("reached the limit of 1 restarts"));
+        assert_eq!(process.restart_status(), Some("gave up".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_timeout_stops_the_run() {
+        let mut process = spawn("sleep 30");
+        assert!(process.timeout_left().is_some_and(|left| left > Duration::ZERO));
+        tokio::time::sleep(Duration::from_millis(250)).await;
+        process.check_timeout(Duration::from_secs(1)).await;
+        assert!(process.timed_out);
+        assert_eq!(process.timeout_left(), Some(Duration::ZERO));
+
+        let status = wait_for_exit(&mut process).await;
+        assert_eq!(status.signal(), Some(libc::SIGTERM));
+        let texts: Vec<&str> = process.output.in_memory().map(|line| line.text.as_str()).collect();
+        assert!(texts.contains(&"── timed out after 200ms, stopping ──"));
+    }
+
+    #[tokio::test]
+    async fn test_on_failure_leaves_successful_runs_alone() {
+        let mut process = spawn_with_policy("true", RestartPolicy::OnFailure);
+        let status = wait_for_exit(&mut process).await;
+        assert!(!process.schedule_restart(status));
+        assert!(process.gave_up.is_none());
+        assert_eq!(process.restart_status(), Some("on-failure".to_string()));
+    }
+}
@@ -0,0 +1,145 @@
+use crate::ansi::indexed_color;
+use ratatui::style::{Color, Modifier, Style};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// When the output pane shows the emulated screen instead of the line buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ScreenMode {
+    /// Only while the child is using the alternate screen (vim, htop, less)
+    #[default]
+    Auto,
+    /// Whenever the child runs under a PTY
+    Always,
+    /// Never; always show the scrolling line buffer
+    Never,
+}
+
+/// An emulated terminal fed with everything the child writes to its PTY, so
+/// full-screen programs can be drawn cell by cell.
+#[derive(Clone)]
+pub struct TerminalScreen {
+    parser: Arc<Mutex<vt100::Parser>>,
+}
+
+// vt100 underflows when a line wraps on a one-row screen, which tiny split panes can produce
+const MIN_ROWS: u16 = 2;
+
+impl TerminalScreen {
+    pub fn new(rows: u16, cols: u16) -> Self {
+        TerminalScreen {
+            parser: Arc::new(Mutex::new(vt100::Parser::new(rows.max(MIN_ROWS), cols.max(1), 0))),
+        }
+    }
+
+    pub fn process(&self, bytes: &[u8]) {
+        self.parser.lock().unwrap().process(bytes);
+    }
+
+    pub fn resize(&self, rows: u16, cols: u16) {
+        self.parser.lock().unwrap().set_size(rows.max(MIN_ROWS), cols.max(1));
+    }
+
+    pub fn in_alternate_screen(&self) -> bool {
+        self.parser.lock().unwrap().screen().alternate_screen()
+    }
+
+    /// Whether the output pane should show this screen rather than the line buffer.
+    pub fn is_shown(&self, mode: ScreenMode) -> bool {
+        match mode {
+            ScreenMode::Auto => self.in_alternate_screen(),
+            ScreenMode::Always => true,
+            ScreenMode::Never => false,
+        }
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, vt100::Parser> {
+        self.parser.lock().unwrap()
+    }
+}
+
+fn convert_color(color: vt100::Color) -> Option<Color> {
+    match color {
+        vt100::Color::Default => None,
+        vt100::Color::Idx(index) => Some(indexed_color(index)),
+        vt100::Color::Rgb(r, g, b) => Some(Color::Rgb(r, g, b)),
+    }
+}
+
+pub fn cell_style(cell: &vt100::Cell) -> Style {
+    let mut style = Style {
+        fg: convert_color(cell.fgcolor()),
+        bg: convert_color(cell.bgcolor()),
+        ..Style::default()
+    };
+    if cell.bold() {
+        style = style.add_modifier(Modifier::BOLD);
+    }
+    if cell.italic() {
+        style = style.add_modifier(Modifier::ITALIC);
+    }
+    if cell.underline() {
+        style = style.add_modifier(Modifier::UNDERLINED);
+    }
+    if cell.inverse() {
+        style = style.add_modifier(Modifier::REVERSED);
+    }
+    style
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_addressing_and_erase() {
+        let screen = TerminalScreen::new(5, 20);
+        screen.process(b"hello world\x1b[1;7HWORLD\x1b[2;1Hsecond\x1b[1;3H\x1b[K");
+        let parser = screen.lock();
+        let contents: Vec<String> = parser.screen().rows(0, 20).take(2).collect();
+        assert_eq!(contents, vec!["he", "second"]);
+    }
+
+    #[test]
+    fn test_alternate_screen_is_tracked() {
+        let screen = TerminalScreen::new(5, 20);
+        assert!(!screen.in_alternate_screen());
+        screen.process(b"\x1b[?1049h");
+        assert!(screen.in_alternate_screen());
+        screen.process(b"\x1b[?1049l");
+        assert!(!screen.in_alternate_screen());
+    }
+
+    #[test]
+    fn test_screen_mode_selection() {
+        let screen = TerminalScreen::new(5, 20);
+        assert!(!screen.is_shown(ScreenMode::Auto));
+        assert!(screen.is_shown(ScreenMode::Always));
+        screen.process(b"\x1b[?1049h");
+        assert!(screen.is_shown(ScreenMode::Auto));
+        assert!(!screen.is_shown(ScreenMode::Never));
+    }
+
+    #[test]
+    fn test_resize_changes_screen_size() {
+        let screen = TerminalScreen::new(5, 20);
+        screen.resize(10, 40);
+        assert_eq!(screen.lock().screen().size(), (10, 40));
+    }
+
+    #[test]
+    fn test_tiny_screen_survives_wrapping() {
+        let screen = TerminalScreen::new(0, 0);
+        screen.resize(1, 4);
+        screen.process(b"wraps around\r\nand again");
+        assert_eq!(screen.lock().screen().size(), (2, 4));
+    }
+
+    #[test]
+    fn test_cell_style() {
+        let screen = TerminalScreen::new(2, 10);
+        screen.process(b"\x1b[1;31;48;2;1;2;3mX");
+        let parser = screen.lock();
+        let style = cell_style(parser.screen().cell(0, 0).unwrap());
+        assert_eq!(style, Style::default().fg(Color::Red).bg(Color::Rgb(1, 2, 3)).add_modifier(Modifier::BOLD));
+    }
+}
@@ -4,6 +4,8 @@ use durable_color_resolver::{resolve_colors, ColorDefinitions};
 use ratatui::style::Color;
 use std::collections::HashMap;
 
+pub const DEFAULT_ERROR_COLOR: Color = Color::Rgb(239, 68, 68);
+
 pub fn default_theme() -> Theme {
     let mut map = HashMap::new();
     map.insert("base".to_string(), "blue-500".to_string());
@@ -13,6 +15,7 @@ pub fn default_theme() -> Theme {
     map.insert("border".to_string(), "darken(base, 40%)".to_string());
     map.insert("text".to_string(), "lighten(base, 80%)".to_string());
     map.insert("background".to_string(), "darken(base, 80%)".to_string());
+    map.insert("error".to_string(), "red-500".to_string());
     map.insert("shade1".to_string(), "lighten(base, 60%)".to_string());
     map.insert("shade2".to_string(), "lighten(base, 50%)".to_string());
     map.insert("shade3".to_string(), "lighten(base, 40%)".to_string());
@@ -32,6 +35,7 @@ pub fn default_theme() -> Theme {
         border: hex_to_rgb(&resolved.0["border"]).unwrap_or(Color::Rgb(17, 24, 39)),
         text: hex_to_rgb(&resolved.0["text"]).unwrap_or(Color::Rgb(255, 255, 255)),
         background: hex_to_rgb(&resolved.0["background"]).unwrap_or(Color::Rgb(0, 0, 0)),
+        error: resolved.0.get("error").and_then(|hex| hex_to_rgb(hex)).unwrap_or(DEFAULT_ERROR_COLOR),
         shades: vec![
             hex_to_rgb(&resolved.0["shade1"]).unwrap_or(Color::Rgb(120, 180, 255)),
             hex_to_rgb(&resolved.0["shade2"]).unwrap_or(Color::Rgb(100, 160, 255)),
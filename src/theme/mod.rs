@@ -16,10 +16,17 @@ pub struct Theme {
     pub text: Color,
     #[serde(with = "color_serde")]
     pub background: Color,
+    /// Color of lines the command wrote to stderr
+    #[serde(with = "color_serde", default = "default_error_color")]
+    pub error: Color,
     #[serde(skip)]
     pub shades: Vec<Color>,
 }
 
+fn default_error_color() -> Color {
+    crate::theme::default::DEFAULT_ERROR_COLOR
+}
+
 impl Default for Theme {
     fn default() -> Self {
         crate::theme::default::default_theme()
@@ -1,13 +1,24 @@
+use crate::ansi::OutputStream;
+use crate::highlight::Highlighter;
+use crate::json_log::JsonLogConfig;
+use crate::process_tree::{ProcessRow, TreeSort};
+use crate::keys::KeySpec;
+use crate::search::{Prompt, Search};
+use crate::scrollback::{Scrollback, ShownLines};
+use crate::terminal::cell_style;
 use crate::theme::Theme;
-use crate::utils::{blend_colors, format_bytes, format_runtime};
+use crate::ui_layout::PaneLayout;
+use crate::ui_output::{OutputView, TimestampMode};
+use crate::utils::{blend_colors, format_bytes, format_exit_status, format_runtime};
 use crate::easing;
 use ratatui::{
-    layout::Rect,
+    layout::{Constraint, Rect},
     style::{Modifier, Style},
-    text::{Line, Span},
-    widgets::{Block, Borders, Paragraph, Wrap, Padding},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Cell, Clear, Paragraph, Row, Table, TableState, Wrap, Padding},
     Frame,
 };
+use std::borrow::Cow;
 use std::time::Duration;
 
 const SPARKLINE_CHARS: &[char] = &['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
@@ -89,27 +100,73 @@ fn generate_sparkline_u64(data: &[u64], width: usize, theme: &Theme) -> Vec<Span
 pub struct DrawContext<'a> {
     pub command: &'a [String],
     pub pwd: &'a str,
+    /// Since the first run started
     pub elapsed: Duration,
+    /// Since the current run started
+    pub uptime: Duration,
     pub pid: u32,
     pub ppid: u32,
     pub animation_frame: u32,
     pub theme: &'a Theme,
-    pub output_lines: &'a [String],
+    /// One output pane per process; the sidebar details are for the selected one
+    pub panes: &'a [PaneContext<'a>],
+    pub selected: usize,
+    /// Matches are highlighted in every pane
+    pub search: Option<&'a Search>,
+    /// The search or filter being typed, shown under the selected pane
+    pub prompt: Option<&'a Prompt>,
+    /// `[[highlight]]` rules applied to every pane's output
+    pub highlighter: &'a Highlighter,
+    /// How JSON log lines are shown compactly
+    pub json: &'a JsonLogConfig,
+    /// A JSON log line pretty-printed in place of the selected pane's output
+    pub expanded_json: Option<&'a str>,
+    /// Lines the pretty-printed JSON is scrolled down
+    pub json_scroll: usize,
+    /// The selected process' tree, listed in place of its output
+    pub process_tree: Option<&'a [ProcessRow]>,
+    pub tree_sort: TreeSort,
+    /// The PID picked in the process list
+    pub tree_selected: Option<u32>,
+    /// A question popped up over everything until it's answered
+    pub confirm: Option<&'a str>,
+    pub layout: PaneLayout,
     pub sidebar_width: u16,
     pub max_command_lines: usize,
     pub cpu_percent: f32,
     pub memory_used: u64,
     pub memory_total: u64,
+    /// Processes and threads in the command's tree; CPU, memory and disk are summed over it
+    pub process_count: usize,
+    pub thread_count: usize,
     pub disk_read: u64,
     pub disk_write: u64,
     pub child_network_rx: u64,
     pub child_network_tx: u64,
+    /// The RX and TX figures are measured; without a network namespace of
+    /// the command's own or packet capture only sockets can be counted
+    pub network_measured: bool,
+    pub network_connections: usize,
+    pub network_listening: usize,
     pub cpu_history: &'a [f32],
     pub memory_history: &'a [u64],
     pub disk_read_history: &'a [u64],
     pub disk_write_history: &'a [u64],
-    pub follow_mode: bool,
-    pub scroll_offset: usize,
+    pub network_rx_history: &'a [u64],
+    pub network_tx_history: &'a [u64],
+    pub connection_history: &'a [u64],
+    pub input_mode: bool,
+    pub watching: bool,
+    pub last_trigger: Option<&'a str>,
+    pub last_run: &'a str,
+    pub restart_count: u32,
+    pub last_exit: Option<std::process::ExitStatus>,
+    /// The restart policy and what it is doing, when there is one
+    pub restart_status: Option<String>,
+    /// Time before `--timeout` stops the run; zero once it has
+    pub timeout_left: Option<Duration>,
+    pub prefix_pending: bool,
+    pub prefix_key: &'a KeySpec,
     pub shine_amplitude: f32,
     pub shine_frequency: f32,
     pub shine_base_intensity: f32,
@@ -121,36 +178,362 @@ pub struct DrawContext<'a> {
     pub shine_width_midpoint: f32,
 }
 
+/// What one process' output pane shows.
+pub struct PaneContext<'a> {
+    pub name: &'a str,
+    pub output: &'a Scrollback,
+    /// Which of the buffered lines are shown, and the search matches
+    pub shown: &'a ShownLines,
+    /// Emulated terminal to draw instead of the output lines, for full-screen programs
+    pub screen: Option<&'a vt100::Screen>,
+    pub follow_mode: bool,
+    pub scroll_offset: usize,
+    /// The streams and filter the pane is shown with
+    pub view: OutputView<'a>,
+    pub timestamps: TimestampMode,
+    /// When the process was first started, for the elapsed-time gutter
+    pub started_at: chrono::DateTime<chrono::Local>,
+    /// The search match last jumped to, by line id
+    pub search_match: Option<usize>,
+    pub stopping: bool,
+    pub restarting: bool,
+    pub waiting_for_changes: bool,
+    /// Time left before an automatic restart
+    pub restart_in: Option<Duration>,
+    pub exited: Option<std::process::ExitStatus>,
+    pub timed_out: bool,
+    pub cpu_percent: f32,
+    pub memory_used: u64,
+}
+
+/// Area of the output pane, border included, for a frame of the given size.
+pub fn output_rect(size: Rect, sidebar_width: u16) -> Rect {
+    Rect::new(0, 0, size.width.saturating_sub(sidebar_width), size.height)
+}
+
+/// Columns and rows available inside the output pane's border. The child's
+/// terminal is sized to match.
+pub fn output_pane_size(size: Rect, sidebar_width: u16) -> (u16, u16) {
+    inner_size(output_rect(size, sidebar_width))
+}
+
+/// Columns and rows inside a pane's border.
+pub fn inner_size(rect: Rect) -> (u16, u16) {
+    (rect.width.saturating_sub(2), rect.height.saturating_sub(2))
+}
+
+/// Area of each of `count` output panes. With tabs they all share the
+/// output area; split stacks them, giving any leftover rows to the last.
+pub fn pane_rects(size: Rect, sidebar_width: u16, count: usize, layout: PaneLayout) -> Vec<Rect> {
+    let main_rect = output_rect(size, sidebar_width);
+    let count = count.max(1);
+    if layout == PaneLayout::Tabs || count == 1 {
+        return vec![main_rect; count];
+    }
+    let height = main_rect.height / count as u16;
+    (0..count)
+        .map(|i| {
+            let y = main_rect.y + height * i as u16;
+            let pane_height = if i + 1 == count { main_rect.height - height * i as u16 } else { height };
+            Rect::new(main_rect.x, y, main_rect.width, pane_height)
+        })
+        .collect()
+}
+
 pub fn draw_ui(f: &mut Frame, context: DrawContext) {
     let size = f.size();
-    let sidebar_width = context.sidebar_width;
-    let main_rect = Rect::new(0, 0, size.width.saturating_sub(sidebar_width), size.height);
-    let sidebar_rect = Rect::new(size.width.saturating_sub(sidebar_width), 0, sidebar_width, size.height);
+    let rects = pane_rects(size, context.sidebar_width, context.panes.len(), context.layout);
 
     // Draw main area with output
-    let output_text = context.output_lines.join("\n");
-    let output_block = Block::default()
+    for (index, (pane, rect)) in context.panes.iter().zip(rects).enumerate() {
+        let selected = index == context.selected;
+        if context.layout == PaneLayout::Tabs && !selected {
+            continue;
+        }
+        draw_pane(f, rect, pane, &context, selected);
+    }
+
+    draw_sidebar(f, size, &context);
+
+    if let Some(message) = context.confirm {
+        draw_confirm(f, size, message, context.theme);
+    }
+}
+
+fn draw_confirm(f: &mut Frame, size: Rect, message: &str, theme: &Theme) {
+    let hint = "y to send, any other key to cancel";
+    let width = (message.chars().count().max(hint.len()) as u16 + 4).min(size.width);
+    let height = 4.min(size.height);
+    let area = Rect::new(size.width.saturating_sub(width) / 2, size.height.saturating_sub(height) / 2, width, height);
+    let text = vec![
+        Line::from(Span::styled(message.to_string(), Style::default().fg(theme.text).add_modifier(Modifier::BOLD))),
+        Line::from(Span::styled(hint, Style::default().fg(theme.secondary))),
+    ];
+    let block = Block::default()
         .borders(Borders::ALL)
-        .title(if context.follow_mode { "Output (Follow)" } else { "Output (Scroll)" })
-        .border_style(Style::default().fg(context.theme.primary))
-        .title_style(Style::default().fg(context.theme.primary).add_modifier(Modifier::BOLD));
+        .padding(Padding::horizontal(1))
+        .border_style(Style::default().fg(theme.error))
+        .style(Style::default().bg(theme.background));
+    f.render_widget(Clear, area);
+    f.render_widget(Paragraph::new(text).block(block), area);
+}
 
-    // Calculate scroll position
-    let total_lines = context.output_lines.len();
-    let visible_height = main_rect.height.saturating_sub(2) as usize; // Subtract border height
-    let scroll_pos = if context.follow_mode {
-        // In follow mode, scroll to show the latest lines
-        total_lines.saturating_sub(visible_height)
+fn draw_pane(f: &mut Frame, rect: Rect, pane: &PaneContext, context: &DrawContext, selected: bool) {
+    let mut status = String::from(if pane.screen.is_some() {
+        "Output (Terminal)"
+    } else if pane.follow_mode {
+        "Output (Follow)"
     } else {
-        // In scroll mode, scroll_offset is lines scrolled up from bottom
-        (total_lines.saturating_sub(visible_height)).saturating_sub(context.scroll_offset)
+        "Output (Scroll)"
+    });
+    if pane.waiting_for_changes {
+        status.push_str(" [WAITING FOR CHANGES]");
+    } else if let Some(restart_in) = pane.restart_in {
+        status.push_str(&format!(" [RESTARTING IN {}s]", restart_in.as_secs_f64().ceil()));
+    } else if pane.restarting {
+        status.push_str(" [RESTARTING: r again to kill]");
+    } else if pane.timed_out {
+        status.push_str(" [TIMED OUT]");
+    } else if pane.stopping {
+        status.push_str(" [STOPPING: ctrl-c again to kill]");
+    } else if let Some(exited) = &pane.exited {
+        status.push_str(&format!(" [EXITED: {}]", format_exit_status(exited)));
+    }
+    if let Some(label) = pane.view.streams.label() {
+        if pane.screen.is_none() {
+            status.push_str(&format!(" [{label}]"));
+        }
+    }
+    if let (Some(filter), None) = (pane.view.filter, pane.screen) {
+        let shown = pane.shown.len(pane.output);
+        let symbol = if filter.invert { '!' } else { '&' };
+        // Lines paged out to disk aren't filtered, so they aren't counted either
+        let in_memory = pane.output.len() - pane.output.spilled();
+        let scope = if pane.output.spilled() > 0 { " in memory" } else { "" };
+        status.push_str(&format!(
+            " [FILTER {symbol}{}: {shown} of {in_memory} lines{scope}]",
+            filter.search.pattern,
+        ));
+    }
+    if context.search.is_some() && pane.screen.is_none() {
+        let matches = pane.shown.matches();
+        match pane.search_match.and_then(|current| matches.binary_search(&current).ok()) {
+            Some(position) => status.push_str(&format!(" [MATCH {}/{}]", position + 1, matches.len())),
+            None => status.push_str(&format!(" [{} MATCHES]", matches.len())),
+        }
+    }
+    let expanded_json = context.expanded_json.filter(|_| selected && pane.screen.is_none());
+    if expanded_json.is_some() {
+        status.push_str(" [JSON: PgUp/PgDn to scroll, e to close]");
+    }
+    let process_tree = context.process_tree.filter(|_| selected);
+    if process_tree.is_some() {
+        status.push_str(&format!(
+            " [PROCESSES by {}: T/K/S/C/U to signal, o to sort, p to close]",
+            context.tree_sort.label()
+        ));
+    }
+    if selected {
+        if context.prefix_pending {
+            status.push_str(&format!(" [INPUT: {} again sends it, any other key goes to druns]", context.prefix_key));
+        } else if context.input_mode {
+            status.push_str(&format!(" [INPUT: {} to leave]", context.prefix_key));
+        }
+    }
+
+    let title_style = Style::default().fg(context.theme.primary).add_modifier(Modifier::BOLD);
+    let title = if context.panes.len() == 1 {
+        Line::from(status)
+    } else if context.layout == PaneLayout::Tabs {
+        // One tab per process, then the selected one's status
+        let mut spans: Vec<Span> = context
+            .panes
+            .iter()
+            .enumerate()
+            .map(|(index, tab)| {
+                let label = format!(" {}:{} ", index + 1, tab.name);
+                if index == context.selected {
+                    Span::styled(label, title_style.add_modifier(Modifier::REVERSED))
+                } else {
+                    Span::styled(label, title_style)
+                }
+            })
+            .collect();
+        spans.push(Span::styled(format!(" {status}"), title_style));
+        Line::from(spans)
+    } else {
+        Line::from(format!("{} — {status}", pane.name))
+    };
+    let border_color = if selected && context.panes.len() > 1 && context.layout == PaneLayout::Split {
+        context.theme.accent
+    } else {
+        context.theme.primary
     };
+    let mut output_block = Block::default()
+        .borders(Borders::ALL)
+        .title(title)
+        .border_style(Style::default().fg(border_color))
+        .title_style(title_style);
+    if let (Some(prompt), true) = (context.prompt, selected) {
+        let invalid = if prompt.invalid { " [INVALID PATTERN]" } else { "" };
+        output_block = output_block.title_bottom(format!(" {}{}_{invalid} ", prompt.kind.symbol(), prompt.input));
+    }
+
+    if let Some(rows) = process_tree {
+        draw_process_tree(f, rect, output_block, rows, context);
+    } else if let Some(screen) = pane.screen {
+        let inner = output_block.inner(rect);
+        f.render_widget(output_block, rect);
+        draw_screen(f, inner, screen);
+    } else if let Some(json) = expanded_json {
+        let text = Paragraph::new(json)
+            .style(Style::default().fg(context.theme.text))
+            .wrap(Wrap { trim: false })
+            .scroll((context.json_scroll.min(u16::MAX as usize) as u16, 0));
+        f.render_widget(text.block(output_block), rect);
+    } else {
+        draw_output_lines(f, rect, output_block, pane, context);
+    }
+}
+
+fn draw_process_tree(f: &mut Frame, rect: Rect, block: Block, rows: &[ProcessRow], context: &DrawContext) {
+    let theme = context.theme;
+    let mut rows = rows.to_vec();
+    context.tree_sort.sort(&mut rows);
+
+    let header_style = Style::default().fg(theme.primary).add_modifier(Modifier::BOLD);
+    let columns = [
+        ("    PID", TreeSort::Pid),
+        ("STATE", TreeSort::State),
+        ("  CPU%", TreeSort::Cpu),
+        ("      RSS", TreeSort::Memory),
+        ("    TIME", TreeSort::Runtime),
+        ("COMMAND", TreeSort::Command),
+    ];
+    let header = Row::new(columns.map(|(name, column)| {
+        let style = if column == context.tree_sort { header_style.add_modifier(Modifier::UNDERLINED) } else { header_style };
+        Cell::from(name).style(style)
+    }));
+    let body = rows.iter().map(|row| {
+        // Children are indented under their parent while in tree order
+        let indent = if context.tree_sort == TreeSort::Tree { "  ".repeat(row.depth) } else { String::new() };
+        Row::new([
+            format!("{:>7}", row.pid),
+            row.state.to_string(),
+            format!("{:>6.1}", row.cpu_percent),
+            format!("{:>9}", format_bytes(row.memory)),
+            format!("{:>8}", format_runtime(row.runtime)),
+            format!("{indent}{}", row.command),
+        ])
+        .style(Style::default().fg(theme.text))
+    });
+    let widths = [
+        Constraint::Length(7),
+        Constraint::Length(6),
+        Constraint::Length(6),
+        Constraint::Length(9),
+        Constraint::Length(8),
+        Constraint::Min(10),
+    ];
+    let table = Table::new(body, widths)
+        .header(header)
+        .block(block)
+        .highlight_style(Style::default().fg(theme.background).bg(theme.accent));
+    // Selecting the row through the state keeps it scrolled into view
+    let selected = rows.iter().position(|row| Some(row.pid) == context.tree_selected);
+    f.render_stateful_widget(table, rect, &mut TableState::default().with_selected(selected));
+}
+
+fn draw_screen(f: &mut Frame, area: Rect, screen: &vt100::Screen) {
+    let (rows, cols) = screen.size();
+    let buffer = f.buffer_mut();
+    for row in 0..rows.min(area.height) {
+        for col in 0..cols.min(area.width) {
+            let Some(cell) = screen.cell(row, col) else { continue };
+            if cell.is_wide_continuation() {
+                continue;
+            }
+            let contents = cell.contents();
+            let symbol = if contents.is_empty() { " " } else { contents.as_str() };
+            buffer.get_mut(area.x + col, area.y + row).set_symbol(symbol).set_style(cell_style(cell));
+        }
+    }
+
+    let (cursor_row, cursor_col) = screen.cursor_position();
+    if !screen.hide_cursor() && cursor_row < area.height.min(rows) && cursor_col < area.width.min(cols) {
+        let cell = buffer.get_mut(area.x + cursor_col, area.y + cursor_row);
+        let style = cell.style().add_modifier(Modifier::REVERSED);
+        cell.set_style(style);
+    }
+}
+
+fn draw_output_lines(f: &mut Frame, main_rect: Rect, output_block: Block, context: &PaneContext, draw: &DrawContext) {
+    let theme = draw.theme;
+    let gutter_style = Style::default().fg(theme.secondary);
+    let match_style = Style::default().fg(theme.background).bg(theme.accent);
+    let current_match_style = Style::default().fg(theme.background).bg(theme.text).add_modifier(Modifier::BOLD);
+
+    // Only the lines that fit in the pane are rendered
+    let (output, shown) = (context.output, context.shown);
+    let visible_height = main_rect.height.saturating_sub(2) as usize; // Subtract border height
+    let max_scroll_up = shown.len(output).saturating_sub(visible_height);
+    // In scroll mode, scroll_offset is lines scrolled up from bottom
+    let top = if context.follow_mode { max_scroll_up } else { max_scroll_up.saturating_sub(context.scroll_offset) };
+    let line_at = |position: usize| shown.index(output, position).and_then(|index| output.get(index));
+    let mut previous = top.checked_sub(1).and_then(line_at).and_then(|line| line.arrived);
+    let lines: Vec<Line> = (top..shown.len(output).min(top + visible_height))
+        .filter_map(|position| shown.index(output, position))
+        .filter_map(|index| output.get(index).map(|line| (output.id(index), line)))
+        .map(|(id, line)| {
+            // JSON log lines are drawn compactly; search and highlights apply to that
+            let line = match draw.json.compact(&line, theme) {
+                Some(compact) => Cow::Owned(compact),
+                None => line,
+            };
+            let search = draw.search.filter(|_| shown.is_match(id));
+            let mut rendered = if search.is_some() || !draw.highlighter.is_empty() {
+                let mut line = line.as_ref().clone();
+                draw.highlighter.apply(&mut line);
+                // Search matches go over the highlight rules
+                if let Some(search) = search {
+                    let style = if context.search_match == Some(id) { current_match_style } else { match_style };
+                    search.highlight(&mut line, style);
+                }
+                line.to_line()
+            } else {
+                line.to_line()
+            };
+            // Colors the program chose for its own spans still win
+            if line.stream == OutputStream::Stderr {
+                rendered.style.fg = Some(theme.error);
+            }
+            if let Some(gutter) = context.timestamps.gutter(line.arrived, context.started_at, previous) {
+                rendered.spans.insert(0, Span::styled(gutter, gutter_style));
+            }
+            previous = line.arrived.or(previous);
+            if line.repeats > 0 {
+                rendered.spans.push(Span::styled(format!(" ×{}", line.repeats + 1), gutter_style));
+            }
+            rendered
+        })
+        .collect();
+
+    // Long lines wrap, so keep the bottom of the window in view
+    let inner_width = main_rect.width.saturating_sub(2).max(1) as usize;
+    let rows: usize = lines.iter().map(|line| line.width().div_ceil(inner_width).max(1)).sum();
+    let scroll_pos = rows.saturating_sub(visible_height);
+    let output_text = Text::from(lines);
 
     let output_paragraph = Paragraph::new(output_text)
         .block(output_block)
         .wrap(Wrap { trim: false })
         .scroll((scroll_pos as u16, 0));
     f.render_widget(output_paragraph, main_rect);
+}
+
+fn draw_sidebar(f: &mut Frame, size: Rect, context: &DrawContext) {
+    let sidebar_width = context.sidebar_width;
+    let sidebar_rect = Rect::new(size.width.saturating_sub(sidebar_width), 0, sidebar_width, size.height);
 
     // Draw sidebar with border
     let block = Block::default()
@@ -270,6 +653,30 @@ pub fn draw_ui(f: &mut Frame, context: DrawContext) {
         )));
     }
 
+    // Every process with its state and current usage, the selected one marked
+    if context.panes.len() > 1 {
+        lines.push(Line::from(""));
+        for (index, pane) in context.panes.iter().enumerate() {
+            let usage = if let Some(status) = &pane.exited {
+                format!("exited {}", format_exit_status(status))
+            } else {
+                format!("{:.0}% {}", pane.cpu_percent, format_bytes(pane.memory_used))
+            };
+            let marker = if index == context.selected { '▶' } else { ' ' };
+            let label_width = content_width.saturating_sub(usage.chars().count() + 1);
+            let label: String = format!("{marker}{} {}", index + 1, pane.name).chars().take(label_width).collect();
+            let style = if index == context.selected {
+                Style::default().fg(context.theme.accent).add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(context.theme.secondary)
+            };
+            lines.push(Line::from(Span::styled(
+                format!("{label:<label_width$} {usage}"),
+                style,
+            )));
+        }
+    }
+
     // Empty line for spacing between runtime and command
     lines.push(Line::from(""));
 
@@ -281,6 +688,25 @@ pub fn draw_ui(f: &mut Frame, context: DrawContext) {
         Style::default().fg(context.theme.secondary).add_modifier(Modifier::BOLD),
     )));
 
+    // Countdown to the timeout
+    if let Some(left) = context.timeout_left {
+        let timeout_value = if left.is_zero() { "expired".to_string() } else { format_runtime(left) };
+        let timeout_text = format!("{:<10}{:>width$}", "Timeout:", timeout_value, width = remaining_width);
+        lines.push(Line::from(Span::styled(
+            timeout_text,
+            Style::default().fg(context.theme.secondary).add_modifier(Modifier::BOLD),
+        )));
+    }
+
+    // Time since the last restart
+    if context.restart_count > 0 {
+        let uptime_text = format!("{:<10}{:>width$}", "Uptime:", format_runtime(context.uptime), width = remaining_width);
+        lines.push(Line::from(Span::styled(
+            uptime_text,
+            Style::default().fg(context.theme.secondary),
+        )));
+    }
+
     // PID
     let pid_value = context.pid.to_string();
     let pid_text = format!("{:<10}{:>width$}", "PID:", pid_value, width = remaining_width);
@@ -297,6 +723,54 @@ pub fn draw_ui(f: &mut Frame, context: DrawContext) {
         Style::default().fg(context.theme.accent),
     )));
 
+    // The command and everything it started
+    let procs_value = format!("{} ({} threads)", context.process_count, context.thread_count);
+    let procs_text = format!("{:<10}{:>width$}", "Procs:", procs_value, width = remaining_width);
+    lines.push(Line::from(Span::styled(
+        procs_text,
+        Style::default().fg(context.theme.accent),
+    )));
+
+    // Restarts and how the previous run ended
+    if context.restart_count > 0 {
+        let restarts_text = format!("{:<10}{:>width$}", "Restarts:", context.restart_count, width = remaining_width);
+        lines.push(Line::from(Span::styled(
+            restarts_text,
+            Style::default().fg(context.theme.accent),
+        )));
+    }
+    if let Some(status) = &context.last_exit {
+        let exit_text = format!("{:<10}{:>width$}", "Last exit:", format_exit_status(status), width = remaining_width);
+        lines.push(Line::from(Span::styled(
+            exit_text,
+            Style::default().fg(context.theme.accent),
+        )));
+    }
+    if let Some(restart_status) = &context.restart_status {
+        let restart_text = format!("{:<10}{:>width$}", "Restart:", restart_status, width = remaining_width);
+        lines.push(Line::from(Span::styled(
+            restart_text,
+            Style::default().fg(context.theme.accent),
+        )));
+    }
+
+    // Watch mode: when the command last started and which change caused it
+    if context.watching {
+        let last_run_text = format!("{:<10}{:>width$}", "Last run:", context.last_run, width = remaining_width);
+        lines.push(Line::from(Span::styled(
+            last_run_text,
+            Style::default().fg(context.theme.accent),
+        )));
+        let trigger = context.last_trigger.unwrap_or("-");
+        let trigger_lines = wrap_command_text(&[format!("Trigger: {trigger}")], content_width, context.max_command_lines);
+        for line in trigger_lines {
+            lines.push(Line::from(Span::styled(
+                line,
+                Style::default().fg(context.theme.secondary),
+            )));
+        }
+    }
+
     // CPU sparkline
     let cpu_sparkline_spans = generate_sparkline(context.cpu_history, content_width, context.theme);
     lines.push(Line::from(cpu_sparkline_spans));
@@ -344,16 +818,25 @@ pub fn draw_ui(f: &mut Frame, context: DrawContext) {
         Style::default().fg(context.theme.accent),
     )));
 
-    // Network usage
-    #[cfg(feature = "experimental-pcap")]
-    {
-        // Network sparkline (using RX for simplicity, but since network is not in history, perhaps skip or use a placeholder)
-        // Since network is not historied, use a static sparkline or skip
-        let network_sparkline_spans = vec![Span::styled(" ".repeat(content_width), Style::default().fg(context.theme.secondary))]; // Placeholder, since no history
-        lines.push(Line::from(network_sparkline_spans));
-        // Padding below flamegraph
-        lines.push(Line::from(""));
+    // Network sparkline (combined RX and TX when they are measured, otherwise connections)
+    let network_history: Vec<u64> = if context.network_measured {
+        context.network_rx_history.iter().zip(context.network_tx_history).map(|(rx, tx)| rx + tx).collect()
+    } else {
+        context.connection_history.to_vec()
+    };
+    let network_sparkline_spans = generate_sparkline_u64(&network_history, content_width, context.theme);
+    lines.push(Line::from(network_sparkline_spans));
+    // Padding below flamegraph
+    lines.push(Line::from(""));
 
+    // Open sockets, and network usage when it is measured
+    let sockets_value = format!("{} conn, {} listen", context.network_connections, context.network_listening);
+    let sockets_text = format!("{:<10}{:>width$}", "Sockets:", sockets_value, width = remaining_width);
+    lines.push(Line::from(Span::styled(
+        sockets_text,
+        Style::default().fg(context.theme.accent),
+    )));
+    if context.network_measured {
         let network_value = format!("RX {} TX {}", format_bytes(context.child_network_rx), format_bytes(context.child_network_tx));
         let network_text = format!("{:<10}{:>width$}", "Network:", network_value, width = remaining_width);
         lines.push(Line::from(Span::styled(
@@ -648,6 +1131,31 @@ mod tests {
         assert_eq!(result, vec!["hello world"]);
     }
 
+    #[test]
+    fn test_output_pane_size() {
+        let size = Rect::new(0, 0, 120, 40);
+        assert_eq!(output_pane_size(size, 30), (88, 38));
+        // Never underflows on tiny terminals
+        assert_eq!(output_pane_size(Rect::new(0, 0, 10, 1), 30), (0, 0));
+    }
+
+    #[test]
+    fn test_pane_rects() {
+        let size = Rect::new(0, 0, 120, 40);
+        let tabs = pane_rects(size, 30, 3, PaneLayout::Tabs);
+        assert_eq!(tabs, vec![Rect::new(0, 0, 90, 40); 3]);
+
+        let split = pane_rects(size, 30, 3, PaneLayout::Split);
+        assert_eq!(
+            split,
+            vec![Rect::new(0, 0, 90, 13), Rect::new(0, 13, 90, 13), Rect::new(0, 26, 90, 14)]
+        );
+        assert_eq!(inner_size(split[2]), (88, 12));
+
+        // A single process fills the output area either way
+        assert_eq!(pane_rects(size, 30, 1, PaneLayout::Split), vec![Rect::new(0, 0, 90, 40)]);
+    }
+
     #[test]
     fn test_wrap_pwd_text() {
         let pwd = "/very/long/path/to/some/directory/that/might/wrap".to_string();
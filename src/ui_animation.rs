@@ -92,8 +92,10 @@ mod tests {
 
     #[test]
     fn test_no_animate_config() {
-        let mut config = AnimationConfig::default();
-        config.no_animate = true;
+        let config = AnimationConfig {
+            no_animate: true,
+            ..AnimationConfig::default()
+        };
         assert!(config.animation_enabled); // animation_enabled is still true in the struct, logic is in main.rs
         assert!(config.no_animate);
     }
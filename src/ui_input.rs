@@ -0,0 +1,29 @@
+fn default_prefix_key() -> String {
+    "ctrl-]".to_string()
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct InputConfig {
+    /// Key that hands the keyboard back to druns while in input mode
+    #[serde(default = "default_prefix_key")]
+    pub prefix_key: String,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        InputConfig {
+            prefix_key: default_prefix_key(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_config_default() {
+        let config = InputConfig::default();
+        assert_eq!(config.prefix_key, "ctrl-]");
+    }
+}
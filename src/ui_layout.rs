@@ -1,7 +1,21 @@
+/// How the output of several processes shares the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum PaneLayout {
+    /// One process at a time, with a tab per process
+    #[default]
+    Tabs,
+    /// Every process in its own pane, stacked vertically
+    Split,
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct LayoutConfig {
     pub sidebar_width: u16,
     pub max_command_lines: usize,
+    /// Layout of the output panes when running several processes
+    #[serde(default)]
+    pub pane_layout: PaneLayout,
 }
 
 impl Default for LayoutConfig {
@@ -9,6 +23,7 @@ impl Default for LayoutConfig {
         LayoutConfig {
             sidebar_width: 30,
             max_command_lines: 3,
+            pane_layout: PaneLayout::Tabs,
         }
     }
 }
@@ -22,5 +37,15 @@ mod tests {
         let config = LayoutConfig::default();
         assert_eq!(config.sidebar_width, 30);
         assert_eq!(config.max_command_lines, 3);
+        assert_eq!(config.pane_layout, PaneLayout::Tabs);
+    }
+
+    #[test]
+    fn test_pane_layout_deserialization() {
+        let config: LayoutConfig =
+            toml::from_str("sidebar_width = 30\nmax_command_lines = 3\npane_layout = \"split\"").unwrap();
+        assert_eq!(config.pane_layout, PaneLayout::Split);
+        let config: LayoutConfig = toml::from_str("sidebar_width = 30\nmax_command_lines = 3").unwrap();
+        assert_eq!(config.pane_layout, PaneLayout::Tabs);
     }
-}
\ No newline at end of file
+}
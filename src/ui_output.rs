@@ -1,12 +1,183 @@
+use crate::ansi::{OutputStream, StyledLine};
+use crate::json_log::JsonLogConfig;
+use crate::search::LineFilter;
+use chrono::{DateTime, Local};
+use std::borrow::Cow;
+
+/// Which of the command's streams the output pane shows. Lines from druns
+/// itself are always shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StreamFilter {
+    #[default]
+    All,
+    Stdout,
+    Stderr,
+}
+
+impl StreamFilter {
+    pub fn shows(self, stream: OutputStream) -> bool {
+        match (self, stream) {
+            (StreamFilter::All, _) | (_, OutputStream::Druns) => true,
+            (StreamFilter::Stdout, stream) => stream == OutputStream::Stdout,
+            (StreamFilter::Stderr, stream) => stream == OutputStream::Stderr,
+        }
+    }
+
+    /// The filter the toggle key switches to next.
+    pub fn next(self) -> Self {
+        match self {
+            StreamFilter::All => StreamFilter::Stdout,
+            StreamFilter::Stdout => StreamFilter::Stderr,
+            StreamFilter::Stderr => StreamFilter::All,
+        }
+    }
+
+    /// Title marker while only one stream is shown.
+    pub fn label(self) -> Option<&'static str> {
+        match self {
+            StreamFilter::All => None,
+            StreamFilter::Stdout => Some("STDOUT ONLY"),
+            StreamFilter::Stderr => Some("STDERR ONLY"),
+        }
+    }
+}
+
+/// Which lines of a process' buffer the output pane shows.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OutputView<'a> {
+    pub streams: StreamFilter,
+    pub filter: Option<&'a LineFilter>,
+    /// JSON log lines are shown, and so filtered and searched, compactly
+    pub json: Option<&'a JsonLogConfig>,
+}
+
+impl OutputView<'_> {
+    pub fn is_unfiltered(&self) -> bool {
+        self.streams == StreamFilter::All && self.filter.is_none()
+    }
+
+    /// Whether the pane shows a line, given as `as_shown` returns it.
+    pub fn shows(&self, shown: &StyledLine) -> bool {
+        self.streams.shows(shown.stream) && self.filter.is_none_or(|filter| filter.shows(shown))
+    }
+
+    /// `line` with the text the pane draws for it, for searching and filtering.
+    pub fn as_shown<'b>(&self, line: &'b StyledLine) -> Cow<'b, StyledLine> {
+        match self.json {
+            Some(json) => json.shown(line),
+            None => Cow::Borrowed(line),
+        }
+    }
+}
+
+/// What the gutter before each output line shows about when it arrived.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampMode {
+    /// No gutter
+    #[default]
+    Off,
+    /// Time of day
+    Absolute,
+    /// Time since the process was first started
+    Elapsed,
+    /// Time since the previous line
+    Delta,
+}
+
+impl TimestampMode {
+    /// The mode the toggle key switches to next.
+    pub fn next(self) -> Self {
+        match self {
+            TimestampMode::Off => TimestampMode::Absolute,
+            TimestampMode::Absolute => TimestampMode::Elapsed,
+            TimestampMode::Elapsed => TimestampMode::Delta,
+            TimestampMode::Delta => TimestampMode::Off,
+        }
+    }
+
+    /// The gutter text for a line that arrived at `arrived`, in a fixed
+    /// width per mode so the output stays aligned. `previous` is when the line
+    /// shown above it arrived; `None` for the first line.
+    pub fn gutter(
+        self,
+        arrived: Option<DateTime<Local>>,
+        started: DateTime<Local>,
+        previous: Option<DateTime<Local>>,
+    ) -> Option<String> {
+        let seconds = |from: DateTime<Local>, to: DateTime<Local>| (to - from).num_milliseconds().max(0) as f64 / 1000.0;
+        let text = match (self, arrived) {
+            (TimestampMode::Off, _) => return None,
+            (TimestampMode::Absolute, Some(arrived)) => arrived.format("%H:%M:%S%.3f").to_string(),
+            (TimestampMode::Elapsed, Some(arrived)) => format!("{:>9.3}s", seconds(started, arrived)),
+            (TimestampMode::Delta, Some(arrived)) => {
+                format!("+{:>8.3}s", seconds(previous.unwrap_or(started), arrived))
+            }
+            (_, None) => String::new(),
+        };
+        let width = match self {
+            TimestampMode::Absolute => 12,
+            _ => 10,
+        };
+        Some(format!("{text:>width$} "))
+    }
+}
+
+/// Which runs of output lines are collapsed into one line with a count. The
+/// log files still get every line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, serde::Serialize, serde::Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DedupMode {
+    /// Every line is shown
+    #[default]
+    Off,
+    /// Consecutive identical lines
+    Exact,
+    /// Consecutive lines that differ only in their digits, such as timestamps and counters
+    Numbers,
+}
+
+impl DedupMode {
+    /// Whether `line` repeats `previous` closely enough to be counted against it.
+    pub fn repeats(self, previous: &StyledLine, line: &StyledLine) -> bool {
+        let comparable = |line: &StyledLine| line.stream != OutputStream::Druns && !line.partial;
+        if previous.stream != line.stream || !comparable(previous) || !comparable(line) {
+            return false;
+        }
+        let without_digits = |text: &str| text.chars().filter(|c| !c.is_ascii_digit()).collect::<String>();
+        match self {
+            DedupMode::Off => false,
+            DedupMode::Exact => previous.text == line.text,
+            DedupMode::Numbers => without_digits(&previous.text) == without_digits(&line.text),
+        }
+    }
+}
+
 #[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct OutputConfig {
     pub max_output_lines: usize,
+    /// Page lines past `max_output_lines` out to a temporary file instead of dropping them
+    #[serde(default)]
+    pub unlimited_scrollback: bool,
+    /// Gutter shown before each output line
+    #[serde(default)]
+    pub timestamps: TimestampMode,
+    /// Start each line in the --log file with the time it arrived
+    #[serde(default)]
+    pub log_timestamps: bool,
+    /// Collapse repeated lines into one with a count
+    #[serde(default)]
+    pub dedup: DedupMode,
 }
 
 impl Default for OutputConfig {
     fn default() -> Self {
         OutputConfig {
             max_output_lines: 1000,
+            unlimited_scrollback: false,
+            timestamps: TimestampMode::Off,
+            log_timestamps: false,
+            dedup: DedupMode::Off,
         }
     }
 }
@@ -19,5 +190,64 @@ mod tests {
     fn test_output_config_default() {
         let config = OutputConfig::default();
         assert_eq!(config.max_output_lines, 1000);
+        assert!(!config.unlimited_scrollback);
+        assert_eq!(config.timestamps, TimestampMode::Off);
+        assert!(!config.log_timestamps);
+        assert_eq!(config.dedup, DedupMode::Off);
+    }
+
+    #[test]
+    fn test_timestamp_gutter() {
+        use chrono::TimeZone;
+        let started = Local.with_ymd_and_hms(2024, 5, 6, 7, 8, 9).unwrap();
+        let previous = started + chrono::Duration::milliseconds(1500);
+        let arrived = started + chrono::Duration::milliseconds(2250);
+        let gutter = |mode: TimestampMode| mode.gutter(Some(arrived), started, Some(previous));
+        assert_eq!(gutter(TimestampMode::Off), None);
+        assert_eq!(gutter(TimestampMode::Absolute).unwrap(), "07:08:11.250 ");
+        assert_eq!(gutter(TimestampMode::Elapsed).unwrap(), "    2.250s ");
+        assert_eq!(gutter(TimestampMode::Delta).unwrap(), "+   0.750s ");
+        // The first line's delta counts from the start
+        assert_eq!(TimestampMode::Delta.gutter(Some(arrived), started, None).unwrap(), "+   2.250s ");
+        assert_eq!(TimestampMode::Elapsed.gutter(None, started, None).unwrap(), "           ");
+    }
+
+    #[test]
+    fn test_dedup_modes() {
+        let line = |text: &str| StyledLine::plain(text);
+        let (first, second) = (line("12:00:01 health check ok"), line("12:00:06 health check ok"));
+        assert!(!DedupMode::Off.repeats(&first, &first));
+        assert!(DedupMode::Exact.repeats(&first, &first.clone()));
+        assert!(!DedupMode::Exact.repeats(&first, &second));
+        assert!(DedupMode::Numbers.repeats(&first, &second));
+        assert!(!DedupMode::Numbers.repeats(&first, &line("12:00:06 health check failed")));
+
+        let stderr = StyledLine { stream: OutputStream::Stderr, ..first.clone() };
+        assert!(!DedupMode::Exact.repeats(&first, &stderr));
+        let separator = StyledLine::styled("── restarted ──", Default::default());
+        assert!(!DedupMode::Exact.repeats(&separator, &separator.clone()));
+    }
+
+    #[test]
+    fn test_stream_filter() {
+        assert!(StreamFilter::All.shows(OutputStream::Stderr));
+        assert!(StreamFilter::Stdout.shows(OutputStream::Stdout));
+        assert!(!StreamFilter::Stdout.shows(OutputStream::Stderr));
+        assert!(!StreamFilter::Stderr.shows(OutputStream::Stdout));
+        assert!(StreamFilter::Stderr.shows(OutputStream::Druns));
+        assert_eq!(StreamFilter::All.next().next().next(), StreamFilter::All);
+    }
+
+    #[test]
+    fn test_filters_match_json_lines_as_shown() {
+        let json = JsonLogConfig::default();
+        let line = StyledLine::plain(r#"{"level":"warn","msg":"disk low"}"#);
+        let filter = |pattern| LineFilter { search: crate::search::Search::new(pattern).unwrap().unwrap(), invert: false };
+        let (warn, quoted) = (filter("WARN "), filter("\"level\""));
+        let view = |filter| OutputView { streams: StreamFilter::All, filter: Some(filter), json: Some(&json) };
+        let shows = |view: OutputView| view.shows(&view.as_shown(&line));
+        assert!(shows(view(&warn)));
+        assert!(!shows(view(&quoted)));
+        assert_eq!(view(&warn).as_shown(&line).text, "WARN  disk low");
     }
 }
\ No newline at end of file
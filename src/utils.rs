@@ -25,6 +25,76 @@ pub fn blend_colors(base: ratatui::style::Color, shine: ratatui::style::Color, i
     ratatui::style::Color::Rgb(blended_r, blended_g, blended_b)
 }
 
+/// Describes how a process ended: its exit code, or the signal that killed it.
+pub fn format_exit_status(status: &std::process::ExitStatus) -> String {
+    use std::os::unix::process::ExitStatusExt;
+    match (status.code(), status.signal()) {
+        (Some(code), _) => format!("code {code}"),
+        (None, Some(signal)) => format!("signal {signal}"),
+        (None, None) => "unknown status".to_string(),
+    }
+}
+
+/// Parses a duration such as `90` (seconds), `1.5s`, `500ms`, `10m`, `2h` or `1h30m`.
+pub fn parse_duration(text: &str) -> Result<std::time::Duration, String> {
+    let invalid = || format!("invalid duration {text:?} (expected e.g. 30s, 10m or 1h30m)");
+    let text = text.trim();
+    let secs = parse_secs(text).ok_or_else(invalid)?;
+    match std::time::Duration::try_from_secs_f64(secs) {
+        Ok(duration) if duration.is_zero() => Err(format!("invalid duration {text:?} (must be more than zero)")),
+        Ok(duration) => Ok(duration),
+        Err(_) => Err(invalid()),
+    }
+}
+
+fn parse_secs(text: &str) -> Option<f64> {
+    if let Ok(secs) = text.parse::<f64>() {
+        return Some(secs);
+    }
+    let mut total = 0.0;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let number_len = rest.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+        let unit_len = rest[number_len..].find(|c: char| c.is_ascii_digit() || c == '.').unwrap_or(rest.len() - number_len);
+        let number: f64 = rest[..number_len].parse().ok()?;
+        let scale = match &rest[number_len..number_len + unit_len] {
+            "ms" => 0.001,
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 3600.0,
+            _ => return None,
+        };
+        total += number * scale;
+        rest = &rest[number_len + unit_len..];
+    }
+    (!text.is_empty()).then_some(total)
+}
+
+/// Writes a duration the way `parse_duration` reads it, e.g. `1h30m`, `1.5s`
+/// or `500ms`.
+pub fn format_duration(duration: std::time::Duration) -> String {
+    if duration < std::time::Duration::from_secs(1) {
+        return format!("{}ms", duration.as_millis());
+    }
+    let secs = duration.as_secs();
+    let (hours, mins, secs) = (secs / 3600, secs / 60 % 60, secs % 60);
+    let mut text = String::new();
+    if hours > 0 {
+        text.push_str(&format!("{hours}h"));
+    }
+    if mins > 0 {
+        text.push_str(&format!("{mins}m"));
+    }
+    let millis = duration.subsec_millis();
+    if millis > 0 {
+        let fraction = format!("{millis:03}");
+        text.push_str(&format!("{secs}.{}s", fraction.trim_end_matches('0')));
+    } else if secs > 0 {
+        text.push_str(&format!("{secs}s"));
+    }
+    text
+}
+
 pub fn format_bytes(bytes: u64) -> String {
     const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
     let mut size = bytes as f64;
@@ -91,6 +161,38 @@ mod tests {
         assert_eq!(result, ratatui::style::Color::Rgb(177, 177, 177));
     }
 
+    #[test]
+    fn test_format_exit_status() {
+        use std::os::unix::process::ExitStatusExt;
+        assert_eq!(format_exit_status(&std::process::ExitStatus::from_raw(0)), "code 0");
+        assert_eq!(format_exit_status(&std::process::ExitStatus::from_raw(3 << 8)), "code 3");
+        assert_eq!(format_exit_status(&std::process::ExitStatus::from_raw(9)), "signal 9");
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("1.5s"), Ok(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("500ms"), Ok(Duration::from_millis(500)));
+        assert_eq!(parse_duration("10m"), Ok(Duration::from_secs(600)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("10x").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("-5").is_err());
+        assert!(parse_duration("0").is_err());
+        assert!(parse_duration("0s").is_err());
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(Duration::from_millis(200)), "200ms");
+        assert_eq!(format_duration(Duration::from_millis(1500)), "1.5s");
+        assert_eq!(format_duration(Duration::from_secs(90)), "1m30s");
+        assert_eq!(format_duration(Duration::from_secs(3600)), "1h");
+        assert_eq!(format_duration(Duration::from_secs(5400)), "1h30m");
+    }
+
     #[test]
     fn test_format_bytes() {
         assert_eq!(format_bytes(0), "0.0B");
@@ -0,0 +1,315 @@
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use tokio::sync::mpsc;
+
+/// One line of a `.gitignore` file or an `--ignore` pattern.
+#[derive(Debug, Clone)]
+struct IgnorePattern {
+    glob: Vec<char>,
+    /// `!pattern`: re-includes paths an earlier pattern ignored
+    negated: bool,
+    /// `pattern/`: only matches directories
+    dir_only: bool,
+    /// Patterns containing a slash match the whole path from the base
+    /// directory, others match any single path component
+    anchored: bool,
+}
+
+impl IgnorePattern {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+        let (negated, line) = match line.strip_prefix('!') {
+            Some(rest) => (true, rest),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (dir_only, line) = match line.strip_suffix('/') {
+            Some(rest) => (true, rest),
+            None => (false, line),
+        };
+        let anchored = line.contains('/');
+        let line = line.strip_prefix('/').unwrap_or(line);
+        if line.is_empty() {
+            return None;
+        }
+        Some(IgnorePattern { glob: line.chars().collect(), negated, dir_only, anchored })
+    }
+
+    fn matches(&self, relative: &str, is_dir: bool) -> bool {
+        if self.dir_only && !is_dir {
+            return false;
+        }
+        let text: Vec<char> = if self.anchored {
+            relative.chars().collect()
+        } else {
+            relative.rsplit('/').next().unwrap_or(relative).chars().collect()
+        };
+        glob_match(&self.glob, &text)
+    }
+}
+
+/// Patterns read from one place, matched relative to the directory they apply to.
+#[derive(Debug, Clone)]
+struct IgnoreFile {
+    base: PathBuf,
+    patterns: Vec<IgnorePattern>,
+}
+
+impl IgnoreFile {
+    /// The verdict of the last matching pattern, if any matches.
+    fn verdict(&self, path: &Path, is_dir: bool) -> Option<bool> {
+        let relative = path.strip_prefix(&self.base).ok()?.to_str()?;
+        if relative.is_empty() {
+            return None;
+        }
+        self.patterns
+            .iter()
+            .rev()
+            .find(|pattern| pattern.matches(relative, is_dir))
+            .map(|pattern| !pattern.negated)
+    }
+}
+
+/// Gitignore-style rules deciding which changes are worth a rerun.
+#[derive(Debug, Clone, Default)]
+pub struct IgnoreRules {
+    files: Vec<IgnoreFile>,
+    paths: Vec<PathBuf>,
+}
+
+impl IgnoreRules {
+    /// Adds patterns that apply to paths under `base`.
+    pub fn add_patterns<S: AsRef<str>>(&mut self, base: &Path, lines: &[S]) {
+        let patterns = lines.iter().filter_map(|line| IgnorePattern::parse(line.as_ref())).collect();
+        self.files.push(IgnoreFile { base: base.to_path_buf(), patterns });
+    }
+
+    /// Adds the `.gitignore` in `dir`, if there is one.
+    pub fn add_gitignore(&mut self, dir: &Path) {
+        if let Ok(contents) = std::fs::read_to_string(dir.join(".gitignore")) {
+            let lines: Vec<&str> = contents.lines().collect();
+            self.add_patterns(dir, &lines);
+        }
+    }
+
+    /// Ignores one specific file, such as druns' own log.
+    pub fn add_path(&mut self, path: &Path) {
+        self.paths.push(path.to_path_buf());
+    }
+
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if self.paths.iter().any(|ignored| ignored == path) {
+            return true;
+        }
+        // A path inside an ignored directory stays ignored whatever later patterns say
+        let ancestors: Vec<&Path> = path.ancestors().skip(1).collect();
+        for ancestor in ancestors.into_iter().rev() {
+            if ancestor.file_name() == Some(".git".as_ref()) || self.check(ancestor, true) {
+                return true;
+            }
+        }
+        path.file_name() == Some(".git".as_ref()) || self.check(path, is_dir)
+    }
+
+    fn check(&self, path: &Path, is_dir: bool) -> bool {
+        // Later files (more specific directories) take precedence
+        self.files
+            .iter()
+            .rev()
+            .find_map(|file| file.verdict(path, is_dir))
+            .unwrap_or(false)
+    }
+}
+
+/// Matches a gitignore glob: `*` and `?` stay within one path component,
+/// `**` spans any number of them, and `[...]` matches a character class.
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.first() {
+        None => text.is_empty(),
+        Some('*') if pattern.get(1) == Some(&'*') => {
+            let rest = &pattern[2..];
+            // "**/" may also match no directories at all
+            let after_slash = rest.strip_prefix(&['/']).unwrap_or(rest);
+            glob_match(after_slash, text) || (0..=text.len()).any(|i| glob_match(rest, &text[i..]))
+        }
+        Some('*') => (0..=text.len())
+            .take_while(|&i| i == 0 || text[i - 1] != '/')
+            .any(|i| glob_match(&pattern[1..], &text[i..])),
+        Some('?') => text.first().is_some_and(|&c| c != '/') && glob_match(&pattern[1..], &text[1..]),
+        Some('[') => match (text.first(), class_end(pattern)) {
+            (Some(&c), Some(end)) => {
+                c != '/' && class_matches(&pattern[1..end], c) && glob_match(&pattern[end + 1..], &text[1..])
+            }
+            // An unterminated class is a literal '['
+            (Some(&'['), None) => glob_match(&pattern[1..], &text[1..]),
+            _ => false,
+        },
+        Some('\\') if pattern.len() > 1 => text.first() == Some(&pattern[1]) && glob_match(&pattern[2..], &text[1..]),
+        Some(c) => text.first() == Some(c) && glob_match(&pattern[1..], &text[1..]),
+    }
+}
+
+/// Index of the `]` closing the class that starts at `pattern[0]`.
+fn class_end(pattern: &[char]) -> Option<usize> {
+    let mut i = 1;
+    if matches!(pattern.get(i), Some('!') | Some('^')) {
+        i += 1;
+    }
+    // A leading ']' is part of the class
+    if pattern.get(i) == Some(&']') {
+        i += 1;
+    }
+    pattern[i..].iter().position(|&c| c == ']').map(|pos| i + pos)
+}
+
+fn class_matches(class: &[char], c: char) -> bool {
+    let (negated, class) = match class.first() {
+        Some('!') | Some('^') => (true, &class[1..]),
+        _ => (false, class),
+    };
+    let mut found = false;
+    let mut i = 0;
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == '-' {
+            found |= (class[i]..=class[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            found |= class[i] == c;
+            i += 1;
+        }
+    }
+    found != negated
+}
+
+/// Watches files and directories (recursively) and reports every changed
+/// path that isn't ignored. Debouncing is left to the receiver.
+pub struct FileWatcher {
+    // Dropping the watcher stops the notifications
+    _watcher: RecommendedWatcher,
+}
+
+impl FileWatcher {
+    pub fn new(
+        paths: &[PathBuf],
+        rules: IgnoreRules,
+    ) -> Result<(Self, mpsc::UnboundedReceiver<PathBuf>), Box<dyn std::error::Error>> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |result: notify::Result<notify::Event>| {
+            let Ok(event) = result else { return };
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)) {
+                return;
+            }
+            for path in event.paths {
+                if !rules.is_ignored(&path, path.is_dir()) {
+                    let _ = tx.send(path);
+                }
+            }
+        })?;
+        for path in paths {
+            let mode = if path.is_dir() { RecursiveMode::Recursive } else { RecursiveMode::NonRecursive };
+            watcher
+                .watch(path, mode)
+                .map_err(|e| format!("Cannot watch {}: {e}", path.display()))?;
+        }
+        Ok((FileWatcher { _watcher: watcher }, rx))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+    use tokio::time::{timeout, Duration};
+
+    fn glob(pattern: &str, text: &str) -> bool {
+        glob_match(&pattern.chars().collect::<Vec<_>>(), &text.chars().collect::<Vec<_>>())
+    }
+
+    fn rules(lines: &[&str]) -> IgnoreRules {
+        let mut rules = IgnoreRules::default();
+        rules.add_patterns(Path::new("/repo"), lines);
+        rules
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob("*.rs", "main.rs"));
+        assert!(!glob("*.rs", "src/main.rs"));
+        assert!(glob("src/**/*.rs", "src/main.rs"));
+        assert!(glob("src/**/*.rs", "src/a/b/main.rs"));
+        assert!(glob("**/target", "target"));
+        assert!(glob("file?.[ch]", "file1.c"));
+        assert!(!glob("file?.[!ch]", "file1.c"));
+        assert!(glob("[a-c]x", "bx"));
+        assert!(glob("\\*", "*"));
+    }
+
+    #[test]
+    fn test_unanchored_patterns_match_any_component() {
+        let rules = rules(&["*.log", "target/", "# comment", ""]);
+        assert!(rules.is_ignored(Path::new("/repo/build.log"), false));
+        assert!(rules.is_ignored(Path::new("/repo/sub/build.log"), false));
+        assert!(rules.is_ignored(Path::new("/repo/target/debug/druns"), false));
+        assert!(rules.is_ignored(Path::new("/repo/sub/target"), true));
+        // "target/" only matches directories
+        assert!(!rules.is_ignored(Path::new("/repo/target"), false));
+        assert!(!rules.is_ignored(Path::new("/repo/src/main.rs"), false));
+    }
+
+    #[test]
+    fn test_anchored_patterns_and_negation() {
+        let rules = rules(&["/docs", "generated/*.rs", "!generated/keep.rs"]);
+        assert!(rules.is_ignored(Path::new("/repo/docs/index.md"), false));
+        assert!(!rules.is_ignored(Path::new("/repo/src/docs/index.md"), false));
+        assert!(rules.is_ignored(Path::new("/repo/generated/a.rs"), false));
+        assert!(!rules.is_ignored(Path::new("/repo/generated/keep.rs"), false));
+    }
+
+    #[test]
+    fn test_exact_paths_are_ignored() {
+        let mut rules = IgnoreRules::default();
+        rules.add_path(Path::new("/repo/druns.log"));
+        assert!(rules.is_ignored(Path::new("/repo/druns.log"), false));
+        assert!(!rules.is_ignored(Path::new("/repo/other.log"), false));
+    }
+
+    #[test]
+    fn test_git_directory_is_always_ignored() {
+        let rules = IgnoreRules::default();
+        assert!(rules.is_ignored(Path::new("/repo/.git/index"), false));
+        assert!(!rules.is_ignored(Path::new("/repo/.github/workflows/ci.yml"), false));
+    }
+
+    #[test]
+    fn test_gitignore_is_read_from_disk() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join(".gitignore"), "*.tmp\n").unwrap();
+        let mut rules = IgnoreRules::default();
+        rules.add_gitignore(dir.path());
+        assert!(rules.is_ignored(&dir.path().join("a.tmp"), false));
+        assert!(!rules.is_ignored(&dir.path().join("a.rs"), false));
+    }
+
+    #[tokio::test]
+    async fn test_watcher_reports_changes_that_are_not_ignored() {
+        let dir = tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let mut rules = IgnoreRules::default();
+        rules.add_patterns(&root, &["*.tmp"]);
+        let (_watcher, mut rx) = FileWatcher::new(std::slice::from_ref(&root), rules).unwrap();
+
+        std::fs::write(root.join("scratch.tmp"), "ignored").unwrap();
+        std::fs::write(root.join("main.rs"), "fn main() {}").unwrap();
+        let changed = timeout(Duration::from_secs(5), rx.recv()).await.unwrap().unwrap();
+        assert_eq!(changed, root.join("main.rs"));
+    }
+
+    #[test]
+    fn test_missing_path_is_an_error() {
+        let dir = tempdir().unwrap();
+        let missing = dir.path().join("missing");
+        assert!(FileWatcher::new(&[missing], IgnoreRules::default()).is_err());
+    }
+}
@@ -0,0 +1,43 @@
+fn default_watch_debounce_ms() -> u64 {
+    300
+}
+
+fn default_watch_gitignore() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct WatchConfig {
+    /// Milliseconds of quiet after a change before the command is rerun
+    #[serde(default = "default_watch_debounce_ms")]
+    pub watch_debounce_ms: u64,
+    /// Extra gitignore-style patterns for changes that shouldn't trigger a rerun
+    #[serde(default)]
+    pub watch_ignore: Vec<String>,
+    /// Skip changes to files the project's .gitignore excludes
+    #[serde(default = "default_watch_gitignore")]
+    pub watch_gitignore: bool,
+}
+
+impl Default for WatchConfig {
+    fn default() -> Self {
+        WatchConfig {
+            watch_debounce_ms: default_watch_debounce_ms(),
+            watch_ignore: Vec::new(),
+            watch_gitignore: default_watch_gitignore(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_watch_config_default() {
+        let config = WatchConfig::default();
+        assert_eq!(config.watch_debounce_ms, 300);
+        assert!(config.watch_ignore.is_empty());
+        assert!(config.watch_gitignore);
+    }
+}